@@ -80,6 +80,22 @@ impl AABB {
         }
     }
 
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
     pub fn pad_to_minimums(&mut self) {
         let delta = 0.0001;
 
@@ -97,6 +113,7 @@ impl AABB {
     pub fn hit(&self, r: &Ray, t: Interval) -> bool {
         let ray_orig = r.origin();
         let ray_dir = r.direction();
+        let mut ray_t = Interval::new(t.min, t.max);
 
         for axis in 0..3 {
             let ax = self.axis_interval(axis);
@@ -115,8 +132,6 @@ impl AABB {
             let t0 = (ax.min - x_y_z_ori) * adinv;
             let t1 = (ax.max - x_y_z_ori) * adinv;
 
-            let mut ray_t = Interval::new(t.min, t.max);
-
             if t0 < t1 {
                 ray_t.min = ray_t.min.max(t0);
                 ray_t.max = ray_t.max.min(t1);
@@ -131,6 +146,58 @@ impl AABB {
         }
         true
     }
+
+    // Slab test with the reciprocal direction precomputed by the caller.
+    pub fn hit_with_inverse(&self, origin: Point3, inv_dir: Vec3, t: Interval) -> bool {
+        let mut t_min = t.min;
+        let mut t_max = t.max;
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let (o, inv) = match axis {
+                0 => (origin.x, inv_dir.x),
+                1 => (origin.y, inv_dir.y),
+                _ => (origin.z, inv_dir.z),
+            };
+
+            let mut t0 = (ax.min - o) * inv;
+            let mut t1 = (ax.max - o) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+
+    // The part of `t` during which the ray is inside the box, if any.
+    pub fn clip(&self, r: &Ray, t: Interval) -> Option<Interval> {
+        let origin = r.origin();
+        let dir = r.direction();
+        let mut clipped = t;
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let inv = 1.0 / dir[axis];
+            let mut t0 = (ax.min - origin[axis]) * inv;
+            let mut t1 = (ax.max - origin[axis]) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            clipped.min = clipped.min.max(t0);
+            clipped.max = clipped.max.min(t1);
+            if clipped.max <= clipped.min {
+                return None;
+            }
+        }
+        Some(clipped)
+    }
 }
 
 impl AABB {
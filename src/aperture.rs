@@ -0,0 +1,88 @@
+use crate::rtw_stb_image::RtwImage;
+use crate::rtweekend::random_double;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Shape of the lens opening, and so of out-of-focus highlights. Samples are
+// points in the unit disk, scaled by the defocus radius.
+#[derive(Clone)]
+pub enum ApertureShape {
+    Circle,
+    // A regular polygon with `blades` corners on the unit circle, turned
+    // by `rotation` degrees.
+    Polygon { blades: u32, rotation: f64 },
+    Image(Arc<ApertureMask>),
+}
+
+impl ApertureShape {
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            ApertureShape::Circle => Vec3::random_in_unit_disk(),
+            ApertureShape::Polygon { blades, rotation } => {
+                let n = (*blades).max(3) as f64;
+                // Every blade spans an equal triangle from the centre.
+                let k = (random_double() * n).floor();
+                let a0 = rotation.to_radians() + 2.0 * PI * k / n;
+                let a1 = a0 + 2.0 * PI / n;
+                let (mut s, mut t) = (random_double(), random_double());
+                if s + t > 1.0 {
+                    s = 1.0 - s;
+                    t = 1.0 - t;
+                }
+                Vec3::new(
+                    s * a0.cos() + t * a1.cos(),
+                    s * a0.sin() + t * a1.sin(),
+                    0.0,
+                )
+            }
+            ApertureShape::Image(mask) => mask.sample(),
+        }
+    }
+}
+
+// An aperture drawn as an image: brighter pixels let more light through.
+// The image is stretched over the square around the unit disk.
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    // Running sum of pixel weights, for picking pixels by brightness.
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    pub fn new(filename: &str) -> Self {
+        let image = RtwImage::new(filename);
+        let (width, height) = (image.width(), image.height());
+
+        let mut cdf = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = image.pixel_data(x, y);
+                total += (pixel[0] as f64 + pixel[1] as f64 + pixel[2] as f64) / (3.0 * 255.0);
+                cdf.push(total);
+            }
+        }
+        if total <= 0.0 {
+            eprintln!("ERROR: Aperture mask '{}' is empty.", filename);
+        }
+
+        Self { width, height, cdf }
+    }
+
+    fn sample(&self) -> Vec3 {
+        let Some(&total) = self.cdf.last().filter(|&&total| total > 0.0) else {
+            return Vec3::random_in_unit_disk();
+        };
+        let target = random_double() * total;
+        let index = self.cdf.partition_point(|&c| c <= target).min(self.cdf.len() - 1);
+        let x = (index % self.width) as f64 + random_double();
+        let y = (index / self.width) as f64 + random_double();
+        Vec3::new(
+            2.0 * x / self.width as f64 - 1.0,
+            1.0 - 2.0 * y / self.height as f64,
+            0.0,
+        )
+    }
+}
@@ -3,75 +3,472 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::hittable_list::HittableList;
 use crate::interval::Interval;
 use crate::ray::Ray;
-use crate::rtweekend;
+use crate::vec3::Point3;
+use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::fmt;
 use std::sync::Arc;
 
-#[derive(Clone)]
+pub(crate) const TRAVERSAL_COST: f64 = 0.125;
+pub(crate) const INTERSECTION_COST: f64 = 1.0;
+// Past this depth splits fall back to halving, which bounds the traversal stack.
+pub(crate) const MAX_SAH_DEPTH: usize = 32;
+// Ranges smaller than this are built serially; spawning tasks costs more.
+const PARALLEL_THRESHOLD: usize = 4096;
 
-pub struct BVHNode {
-    left: Option<Arc<dyn Hittable>>,
-    right: Option<Arc<dyn Hittable>>,
-    bbox: AABB,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitMethod {
+    // Sort by box minimum along the longest axis and split at the middle object.
+    Median,
+    // Binned surface area heuristic over all three axes.
+    Sah,
 }
 
-impl BVHNode {
-    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis_index: usize) -> Ordering {
-        let a_box = a.as_ref().bounding_box();
-        let a_axis_interval = a_box.axis_interval(axis_index);
-        let b_box = b.as_ref().bounding_box();
-        let b_axis_interval = b_box.axis_interval(axis_index);
-        a_axis_interval
-            .min
-            .partial_cmp(&b_axis_interval.min)
-            .unwrap_or(Ordering::Equal)
+#[derive(Clone, Copy, Debug)]
+pub struct BVHOptions {
+    pub split_method: SplitMethod,
+    pub max_leaf_size: usize,
+    pub bin_count: usize,
+    // Builds subtrees and bins on the rayon pool. The tree is identical to
+    // the serial build either way.
+    pub parallel: bool,
+}
+
+impl BVHOptions {
+    pub fn median() -> Self {
+        Self {
+            split_method: SplitMethod::Median,
+            max_leaf_size: 1,
+            bin_count: 0,
+            parallel: true,
+        }
     }
-    fn box_x_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 0)
+
+    pub fn sah(max_leaf_size: usize, bin_count: usize) -> Self {
+        Self {
+            split_method: SplitMethod::Sah,
+            max_leaf_size: max_leaf_size.clamp(1, u16::MAX as usize),
+            bin_count: bin_count.max(2),
+            parallel: true,
+        }
     }
-    fn box_y_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 1)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BVHStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub sah_cost: f64,
+}
+
+impl fmt::Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, SAH cost {:.3}",
+            self.node_count, self.leaf_count, self.max_depth, self.sah_cost
+        )
     }
-    fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
-        Self::box_compare(a, b, 2)
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BuildPrim {
+    pub index: usize,
+    pub bbox: AABB,
+    pub centroid: Point3,
+}
+
+impl BuildPrim {
+    pub fn from_objects<T: Hittable + ?Sized>(
+        objects: &[Arc<T>],
+        options: &BVHOptions,
+    ) -> Vec<BuildPrim> {
+        let make = |(index, object): (usize, &Arc<T>)| {
+            let bbox = object.bounding_box();
+            BuildPrim {
+                index,
+                bbox,
+                centroid: bbox.centroid(),
+            }
+        };
+        if options.parallel {
+            objects.par_iter().enumerate().map(make).collect()
+        } else {
+            objects.iter().enumerate().map(make).collect()
+        }
     }
 }
 
-impl BVHNode {
-    pub fn new_from_list(list: &mut HittableList) -> Self {
-        let len = list.objects.len();
-        BVHNode::new(&mut list.objects, 0, len)
+// Intermediate tree shared by the BVH layouts. Leaves refer to a range of the
+// reordered `BuildPrim` slice.
+pub(crate) enum BuildNode {
+    Leaf {
+        bbox: AABB,
+        start: usize,
+        count: usize,
+    },
+    Interior {
+        bbox: AABB,
+        axis: usize,
+        left: Box<BuildNode>,
+        right: Box<BuildNode>,
+    },
+}
+
+impl BuildNode {
+    pub fn bbox(&self) -> AABB {
+        match self {
+            BuildNode::Leaf { bbox, .. } => *bbox,
+            BuildNode::Interior { bbox, .. } => *bbox,
+        }
+    }
+
+    pub fn build(prims: &mut [BuildPrim], options: &BVHOptions) -> BuildNode {
+        Self::build_range(prims, 0, 0, options)
     }
-    pub fn new(objects: &mut Vec<Arc<dyn Hittable>>, start: usize, end: usize) -> Self {
-        let mut bbox = AABB::empty();
-        for object_index in start..end {
-            bbox = AABB::from_boxes(bbox, objects[object_index].bounding_box());
+
+    fn build_range(
+        prims: &mut [BuildPrim],
+        start: usize,
+        depth: usize,
+        options: &BVHOptions,
+    ) -> BuildNode {
+        let parallel = options.parallel && prims.len() >= PARALLEL_THRESHOLD;
+        let (bbox, centroid_bounds) = Self::bounds(prims, parallel);
+
+        let count = prims.len();
+        if count <= options.max_leaf_size.max(1) && options.split_method == SplitMethod::Median {
+            return BuildNode::Leaf { bbox, start, count };
         }
-        let axis = bbox.longest_axis();
-        let comparator: Box<dyn Fn(&Arc<dyn Hittable>, &Arc<dyn Hittable>) -> Ordering> = match axis
-        {
-            0 => Box::new(BVHNode::box_x_compare),
-            1 => Box::new(BVHNode::box_y_compare),
-            _ => Box::new(BVHNode::box_z_compare),
+
+        let split = match options.split_method {
+            SplitMethod::Sah if depth < MAX_SAH_DEPTH => {
+                Self::split_sah(prims, &bbox, &centroid_bounds, parallel, options)
+            }
+            SplitMethod::Sah if count <= options.max_leaf_size => None,
+            _ => Some(Self::split_median(prims, &bbox)),
         };
-        let object_span = end - start;
-        let (left, right) = if object_span == 1 {
-            (Some(objects[start].clone()), Some(objects[start].clone()))
-        } else if object_span == 2 {
+
+        match split {
+            Some((axis, mid)) => {
+                let (left_prims, right_prims) = prims.split_at_mut(mid);
+                let (left, right) = if parallel {
+                    rayon::join(
+                        || Self::build_range(left_prims, start, depth + 1, options),
+                        || Self::build_range(right_prims, start + mid, depth + 1, options),
+                    )
+                } else {
+                    (
+                        Self::build_range(left_prims, start, depth + 1, options),
+                        Self::build_range(right_prims, start + mid, depth + 1, options),
+                    )
+                };
+                BuildNode::Interior {
+                    bbox,
+                    axis,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            }
+            None => BuildNode::Leaf { bbox, start, count },
+        }
+    }
+
+    // Returns the bounds of the primitive boxes and of their centroids.
+    fn bounds(prims: &[BuildPrim], parallel: bool) -> (AABB, AABB) {
+        let fold = |(bbox, centroids): (AABB, AABB), prim: &BuildPrim| {
             (
-                Some(objects[start].clone()),
-                Some(objects[start + 1].clone()),
+                AABB::from_boxes(bbox, prim.bbox),
+                AABB::from_boxes(centroids, AABB::from_points(prim.centroid, prim.centroid)),
             )
+        };
+        let empty = || (AABB::empty(), AABB::empty());
+        if parallel {
+            prims.par_iter().fold(empty, fold).reduce(empty, |a, b| {
+                (AABB::from_boxes(a.0, b.0), AABB::from_boxes(a.1, b.1))
+            })
         } else {
-            objects[start..end].sort_by(comparator);
-            let mid = start + object_span / 2;
-            (
-                Some((Arc::new(BVHNode::new(objects, start, mid))) as Arc<dyn Hittable>),
-                Some((Arc::new(BVHNode::new(objects, mid, end))) as Arc<dyn Hittable>),
-            )
+            prims.iter().fold(empty(), fold)
+        }
+    }
+
+    // Only the middle element needs to land in place, so a selection replaces
+    // the full sort.
+    fn split_median(prims: &mut [BuildPrim], bbox: &AABB) -> (usize, usize) {
+        let axis = bbox.longest_axis();
+        let mid = prims.len() / 2;
+        prims.select_nth_unstable_by(mid, |a, b| {
+            a.bbox
+                .axis_interval(axis)
+                .min
+                .partial_cmp(&b.bbox.axis_interval(axis).min)
+                .unwrap_or(Ordering::Equal)
+        });
+        (axis, mid)
+    }
+
+    fn compute_bins(
+        prims: &[BuildPrim],
+        axis: usize,
+        extent: &Interval,
+        bin_count: usize,
+        parallel: bool,
+    ) -> (Vec<usize>, Vec<AABB>) {
+        if parallel {
+            // Counts add and boxes union exactly, so the chunking order cannot
+            // change the result.
+            return prims
+                .par_chunks(PARALLEL_THRESHOLD / 4)
+                .map(|chunk| Self::compute_bins(chunk, axis, extent, bin_count, false))
+                .reduce(
+                    || (vec![0usize; bin_count], vec![AABB::empty(); bin_count]),
+                    |(mut counts, mut bounds), (other_counts, other_bounds)| {
+                        for b in 0..bin_count {
+                            counts[b] += other_counts[b];
+                            bounds[b] = AABB::from_boxes(bounds[b], other_bounds[b]);
+                        }
+                        (counts, bounds)
+                    },
+                );
+        }
+
+        let mut counts = vec![0usize; bin_count];
+        let mut bounds = vec![AABB::empty(); bin_count];
+        for prim in prims.iter() {
+            let b = Self::bin_index(prim, axis, extent, bin_count);
+            counts[b] += 1;
+            bounds[b] = AABB::from_boxes(bounds[b], prim.bbox);
+        }
+        (counts, bounds)
+    }
+
+    // Returns `None` when a leaf is cheaper than any split.
+    fn split_sah(
+        prims: &mut [BuildPrim],
+        bbox: &AABB,
+        centroid_bounds: &AABB,
+        parallel: bool,
+        options: &BVHOptions,
+    ) -> Option<(usize, usize)> {
+        let count = prims.len();
+        if count <= 1 {
+            return None;
+        }
+
+        let bin_count = options.bin_count.max(2);
+        let parent_area = bbox.surface_area().max(f64::MIN_POSITIVE);
+        let mut best: Option<(usize, usize, f64)> = None;
+
+        for axis in 0..3 {
+            let extent = *centroid_bounds.axis_interval(axis);
+            if extent.size() <= 0.0 {
+                continue;
+            }
+
+            let (counts, bounds) = Self::compute_bins(prims, axis, &extent, bin_count, parallel);
+
+            // Sweep from the right once so each split cost is O(1) on the left sweep.
+            let mut right_area = vec![0.0; bin_count];
+            let mut right_count = vec![0usize; bin_count];
+            let mut acc_box = AABB::empty();
+            let mut acc_count = 0;
+            for b in (1..bin_count).rev() {
+                acc_box = AABB::from_boxes(acc_box, bounds[b]);
+                acc_count += counts[b];
+                right_area[b] = acc_box.surface_area();
+                right_count[b] = acc_count;
+            }
+
+            acc_box = AABB::empty();
+            acc_count = 0;
+            for b in 0..bin_count - 1 {
+                acc_box = AABB::from_boxes(acc_box, bounds[b]);
+                acc_count += counts[b];
+                if acc_count == 0 || right_count[b + 1] == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + INTERSECTION_COST
+                        * (acc_count as f64 * acc_box.surface_area()
+                            + right_count[b + 1] as f64 * right_area[b + 1])
+                        / parent_area;
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, b, cost));
+                }
+            }
+        }
+
+        let leaf_cost = INTERSECTION_COST * count as f64;
+        let (axis, split_bin, cost) = match best {
+            Some(best) => best,
+            // Every centroid coincides; only a leaf or an arbitrary halving is left.
+            None if count <= options.max_leaf_size => return None,
+            None => return Some(Self::split_median(prims, bbox)),
+        };
+        if count <= options.max_leaf_size && leaf_cost <= cost {
+            return None;
+        }
+
+        let extent = *centroid_bounds.axis_interval(axis);
+        let mut mid = 0;
+        for i in 0..count {
+            if Self::bin_index(&prims[i], axis, &extent, bin_count) <= split_bin {
+                prims.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some((axis, mid))
+    }
+
+    fn bin_index(prim: &BuildPrim, axis: usize, extent: &Interval, bin_count: usize) -> usize {
+        let c = match axis {
+            0 => prim.centroid.x,
+            1 => prim.centroid.y,
+            _ => prim.centroid.z,
         };
-        //let bbox = AABB::from_boxes(left.as_ref().unwrap().bounding_box(), right.as_ref().unwrap().bounding_box());
-        BVHNode { left, right, bbox }
+        let b = (bin_count as f64 * (c - extent.min) / extent.size()) as usize;
+        b.min(bin_count - 1)
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        match self {
+            BuildNode::Leaf { count, .. } => BVHStats {
+                node_count: 1,
+                leaf_count: 1,
+                max_depth: 1,
+                sah_cost: INTERSECTION_COST * *count as f64,
+            },
+            BuildNode::Interior {
+                bbox, left, right, ..
+            } => Self::combine_stats(
+                bbox,
+                left.stats(),
+                &left.bbox(),
+                right.stats(),
+                &right.bbox(),
+            ),
+        }
+    }
+
+    fn combine_stats(
+        bbox: &AABB,
+        left: BVHStats,
+        left_box: &AABB,
+        right: BVHStats,
+        right_box: &AABB,
+    ) -> BVHStats {
+        let area = bbox.surface_area();
+        let sah_cost = if area > 0.0 {
+            TRAVERSAL_COST
+                + (left_box.surface_area() * left.sah_cost
+                    + right_box.surface_area() * right.sah_cost)
+                    / area
+        } else {
+            TRAVERSAL_COST + left.sah_cost + right.sah_cost
+        };
+        BVHStats {
+            node_count: 1 + left.node_count + right.node_count,
+            leaf_count: left.leaf_count + right.leaf_count,
+            max_depth: 1 + left.max_depth.max(right.max_depth),
+            sah_cost,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BVHNode {
+    left: Option<Arc<dyn Hittable>>,
+    right: Option<Arc<dyn Hittable>>,
+    bbox: AABB,
+    stats: BVHStats,
+}
+
+impl BVHNode {
+    pub fn new_from_list(list: &mut HittableList) -> Self {
+        let len = list.objects.len();
+        BVHNode::new(&list.objects, 0, len)
+    }
+
+    pub fn new(objects: &[Arc<dyn Hittable>], start: usize, end: usize) -> Self {
+        BVHNode::new_with_options(&objects[start..end], &BVHOptions::median())
+    }
+
+    pub fn new_with_options(objects: &[Arc<dyn Hittable>], options: &BVHOptions) -> Self {
+        let mut prims = BuildPrim::from_objects(objects, options);
+        let root = BuildNode::build(&mut prims, options);
+        match BVHNode::from_build(&root, &prims, objects) {
+            BuiltChild::Node(node) => node,
+            BuiltChild::Leaf(leaf) => BVHNode {
+                left: Some(leaf),
+                right: None,
+                bbox: root.bbox(),
+                stats: root.stats(),
+            },
+        }
+    }
+
+    fn from_build(
+        node: &BuildNode,
+        prims: &[BuildPrim],
+        objects: &[Arc<dyn Hittable>],
+    ) -> BuiltChild {
+        match node {
+            BuildNode::Leaf { start, count, .. } => {
+                if *count == 1 {
+                    return BuiltChild::Leaf(objects[prims[*start].index].clone());
+                }
+                let mut list = HittableList::new();
+                for prim in &prims[*start..*start + *count] {
+                    list.add(objects[prim.index].clone());
+                }
+                BuiltChild::Leaf(Arc::new(list))
+            }
+            BuildNode::Interior {
+                bbox, left, right, ..
+            } => {
+                let left_child = BVHNode::from_build(left, prims, objects);
+                let right_child = BVHNode::from_build(right, prims, objects);
+                let stats = BuildNode::combine_stats(
+                    bbox,
+                    left_child.stats(left),
+                    &left.bbox(),
+                    right_child.stats(right),
+                    &right.bbox(),
+                );
+                BuiltChild::Node(BVHNode {
+                    left: Some(left_child.into_hittable()),
+                    right: Some(right_child.into_hittable()),
+                    bbox: *bbox,
+                    stats,
+                })
+            }
+        }
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        self.stats
+    }
+}
+
+enum BuiltChild {
+    Node(BVHNode),
+    Leaf(Arc<dyn Hittable>),
+}
+
+impl BuiltChild {
+    fn stats(&self, build: &BuildNode) -> BVHStats {
+        match self {
+            BuiltChild::Node(node) => node.stats,
+            BuiltChild::Leaf(_) => build.stats(),
+        }
+    }
+
+    fn into_hittable(self) -> Arc<dyn Hittable> {
+        match self {
+            BuiltChild::Node(node) => Arc::new(node),
+            BuiltChild::Leaf(leaf) => leaf,
+        }
     }
 }
 
@@ -92,6 +489,14 @@ impl Hittable for BVHNode {
         }
         hit_left || hit_right
     }
+    fn occluded(&self, r: &Ray, t: Interval) -> bool {
+        self.bbox.hit(r, t)
+            && (self.left.as_ref().is_some_and(|left| left.occluded(r, t))
+                || self
+                    .right
+                    .as_ref()
+                    .is_some_and(|right| right.occluded(r, t)))
+    }
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
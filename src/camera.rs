@@ -1,39 +1,231 @@
-use crate::color::{Color, write_color};
+use crate::aperture::ApertureShape;
+use crate::color::{Color, read_image, write_color};
+use crate::environment::{Environment, EnvironmentPdf};
+use crate::filter::{Filter, FilterSampler};
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
+use crate::light::Light;
+use crate::material::{Material, scatter_record};
+use crate::pdf::{MixturePdf, Pdf};
 use crate::ray::Ray;
 use crate::rtweekend::{INFINITY, degrees_to_radians};
+use crate::tile::{CropRegion, Tile, TileOrder};
 use crate::vec3::{Point3, Vec3};
 use crate::{color, rtweekend};
 use std::f64::consts::PI;
 use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
 
-pub struct Camera {
-    pub aspect_ratio: f64,
-    pub image_width: usize,
-    image_height: usize,
+// How much light the shutter lets through over its open interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShutterCurve {
+    // Fully open from shutter_open to shutter_close.
+    Box,
+    // Opening linearly to a peak halfway through, then closing again.
+    Triangle,
+}
+
+impl ShutterCurve {
+    // Maps a uniform sample in [0, 1) to a fraction of the open interval.
+    fn sample(&self, u: f64) -> f64 {
+        match self {
+            ShutterCurve::Box => u,
+            ShutterCurve::Triangle => {
+                if u < 0.5 {
+                    (u / 2.0).sqrt()
+                } else {
+                    1.0 - ((1.0 - u) / 2.0).sqrt()
+                }
+            }
+        }
+    }
+}
+
+// How fisheye lenses map the angle off the optical axis to image radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    // Radius proportional to the angle.
+    Equidistant,
+    // Equal areas of the image see equal solid angles.
+    Equisolid,
+}
+
+// How pixels map to ray directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Thin-lens pinhole camera with `vfov` and defocus blur.
+    Perspective,
+    // Parallel rays along the view direction, through a viewport `height`
+    // scene units tall.
+    Orthographic { height: f64 },
+    // A circular image inscribed in the frame, covering `fov` degrees
+    // across its diameter; pixels outside the circle stay black.
+    Fisheye { mapping: FisheyeMapping, fov: f64 },
+    // The full sphere as longitude/latitude; use a 2:1 aspect ratio.
+    Equirectangular,
+    // Six 90 degree faces in a 3x2 grid: right, left, up on the top row and
+    // down, front, back below; use a 3:2 aspect ratio.
+    CubeMap,
+}
+
+// Which eye a pass is rendered for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eye {
+    Center,
+    Left,
+    Right,
+}
+
+impl Eye {
+    // Signed offset along the camera's right vector, in units of the
+    // interpupillary distance.
+    fn offset(&self) -> f64 {
+        match self {
+            Eye::Center => 0.0,
+            Eye::Left => -0.5,
+            Eye::Right => 0.5,
+        }
+    }
+}
+
+// How `render_stereo` packs both eyes into one image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    // Left eye above the right, as VR players expect for 360 video.
+    TopBottom,
+    SideBySide,
+}
+
+// Photographic settings: the f-number sets the lens opening, the shutter
+// speed how many seconds of scene time the shutter stays open, and with the
+// ISO they fix how strongly the film responds to light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub f_number: f64,
+    pub shutter_speed: f64,
+    pub iso: f64,
+}
+
+impl Exposure {
+    pub fn new(f_number: f64, shutter_speed: f64, iso: f64) -> Self {
+        Self {
+            f_number,
+            shutter_speed,
+            iso,
+        }
+    }
+
+    // Exposure value at ISO 100. Settings with equal EV give equally bright
+    // images; each step up lets in half as much light.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    // Scales luminance in cd/m^2 so that the film saturates at 1, following
+    // the saturation-based speed of ISO 12232 (78 / 0.65 = 1.2).
+    fn film_scale(&self) -> f64 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
+
+// Where the camera is and how its viewport lies, at one moment.
+#[derive(Debug, Clone, Copy)]
+struct ViewFrame {
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    // Normal of the plane of sharp focus, which passes through the point
+    // focus_dist ahead on the optical axis.
+    focus_normal: Vec3,
+}
+
+pub struct Camera {
+    pub aspect_ratio: f64,
+    pub image_width: usize,
+    image_height: usize,
     pub samples_per_pixel: u32,
-    pixel_samples_scale: f64,
     sqrt_spp: u32,
     recip_sqrt_spp: f64,
     pub max_depth: usize,
+    // Weights samples by their distance from the pixel centre.
+    pub filter: Filter,
+    filter_sampler: FilterSampler,
     pub background: Color,
+    // Lights the scene in place of the background, and is sampled
+    // alongside each material's own directions.
+    pub environment: Option<Arc<dyn Environment>>,
+    // Point, spot and directional lights, lit by a shadow ray at every
+    // diffuse bounce since no scattered ray can find them.
+    pub lights: Vec<Arc<dyn Light>>,
     pub vfov: f64,
     pub lookfrom: Point3,
     pub lookat: Point3,
     pub vup: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
     pub defocus_angle: f64,
     pub focus_dist: f64,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
+    // Lens shift slides the image window by these fractions of its width
+    // and height without turning the camera, so verticals stay parallel.
+    pub shift_x: f64,
+    pub shift_y: f64,
+    // Lens tilt and swing in degrees, about the camera's horizontal and
+    // vertical axes. By the Scheimpflug principle the plane of focus then
+    // pivots about a hinge line f / sin(angle) from the lens, so a few
+    // degrees turn it almost edge-on.
+    pub tilt: f64,
+    pub swing: f64,
+    pub aperture: ApertureShape,
+    // Cat-eye vignetting: the lens barrel, as wide as the aperture, slides
+    // off-centre by this fraction of its radius at the image corners and
+    // blocks what falls outside it.
+    pub vignetting: f64,
+    // Lateral chromatic aberration: red is magnified by this fraction at the
+    // image edge and blue shrunk by as much.
+    pub chromatic_aberration: f64,
+    // When set, takes the place of defocus_angle and shutter_close, and
+    // radiance is taken to be in cd/m^2.
+    pub exposure: Option<Exposure>,
+    // Brightens the image by this many stops, or darkens it if negative.
+    pub exposure_compensation: f64,
+    // Film height in millimetres, which with vfov gives the focal length.
+    pub sensor_height: f64,
+    // Length of one scene unit, for sizing the lens opening.
+    pub meters_per_unit: f64,
+    film_scale: f64,
+    // defocus_angle and shutter_close as rendered, after any exposure.
+    lens_angle: f64,
+    shutter_end: f64,
+    pub projection: Projection,
+    // Stereo eyes sit this far apart along the camera's right vector, and
+    // their off-axis frusta line up on the plane convergence_dist away.
+    pub interpupillary_distance: f64,
+    pub convergence_dist: f64,
+    // Rays are spread over [shutter_open, shutter_close] following the curve.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub shutter_curve: ShutterCurve,
+    // Values reached at shutter_close, for pans and zooms; the view moves
+    // linearly from lookfrom/lookat/vfov while the shutter is open.
+    pub lookfrom_close: Option<Point3>,
+    pub lookat_close: Option<Point3>,
+    pub vfov_close: Option<f64>,
+    // Renders only this part of the image. The output is just the crop,
+    // unless composite_onto names an earlier full-size render (not the file
+    // being written) to paste it into.
+    pub crop: Option<CropRegion>,
+    pub composite_onto: Option<String>,
+    // Threads take square tiles this many pixels across, in this order.
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    frame: ViewFrame,
+    animated: bool,
 }
 
 impl Camera {
@@ -42,33 +234,79 @@ impl Camera {
             return Color::new(0.0, 0.0, 0.0);
         }
         let mut rec = HitRecord::default();
-        if !world.hit(r, Interval::new(0.001, INFINITY), &mut rec) {
-            return self.background;
-        }
-        let mut scattered = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
-        let mut attenuation = Color::new(0.0, 0.0, 0.0);
-        let mut pdf_value = 0.0;
-        if rec.mat.is_some() {
-            let rec_ = rec.clone();
-            let color_from_emission = rec.clone().mat.unwrap().emitted(rec.u, rec.v, &rec.p);
-            let mut color_from_scatter = Color::new(0.0, 0.0, 0.0);
-            let rec__ = rec_.clone();
-            if rec_.mat.unwrap().scatter(
-                r,
-                &rec__,
-                &mut attenuation,
-                &mut scattered,
-                &mut pdf_value,
-            ) {
-                let scattering_pdf = rec.clone().mat.unwrap().scattering_pdf(r, &rec, &scattered);
-                let pdf_value = scattering_pdf;
-                color_from_scatter =
-                    attenuation * scattering_pdf * self.ray_color(&scattered, depth - 1, world)
-                        / pdf_value;
+        // Secondary rays start from offset origins, so no epsilon is needed.
+        if !world.hit(r, Interval::new(0.0, INFINITY), &mut rec) {
+            // Camera rays only see the environment if it is visible.
+            return match &self.environment {
+                Some(env) if env.visible() || depth < self.max_depth => env.radiance(r.direction()),
+                _ => self.background,
+            };
+        }
+        let Some(mat) = rec.mat.clone() else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+
+        let color_from_emission = mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
+        let mut srec = scatter_record::default();
+        if !mat.scatter(r, &rec, &mut srec) {
+            return color_from_emission;
+        }
+
+        if srec.skip_pdf {
+            return color_from_emission
+                + srec.attenuation * self.ray_color(&srec.skip_pdf_ray, depth - 1, world);
+        }
+
+        let Some(pdf) = srec.pdf_ptr else {
+            return color_from_emission;
+        };
+        let color_from_lights =
+            srec.attenuation * self.direct_light(r, &rec, mat.as_ref(), world);
+        let pdf: Arc<dyn Pdf> = match &self.environment {
+            Some(env) => Arc::new(MixturePdf::new(
+                Arc::new(EnvironmentPdf::new(env.clone())),
+                pdf,
+            )),
+            None => pdf,
+        };
+        let scattered = rec.spawn_ray(pdf.generate(), r.time());
+        let pdf_value = pdf.value(scattered.direction());
+        if pdf_value <= 0.0 {
+            return color_from_emission + color_from_lights;
+        }
+        let scattering_pdf = mat.scattering_pdf(r, &rec, &scattered);
+
+        let color_from_scatter = srec.attenuation
+            * scattering_pdf
+            * self.ray_color(&scattered, depth - 1, world)
+            / pdf_value;
+        color_from_emission + color_from_lights + color_from_scatter
+    }
+
+    // Light from the delta lights that reaches the hit point unblocked,
+    // before the material's attenuation.
+    fn direct_light(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        world: &dyn Hittable,
+    ) -> Color {
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        for light in &self.lights {
+            let Some(sample) = light.sample(rec.p) else {
+                continue;
+            };
+            let shadow_ray = rec.spawn_ray(sample.direction, r.time());
+            let scattering_pdf = mat.scattering_pdf(r, rec, &shadow_ray);
+            if scattering_pdf <= 0.0
+                || world.occluded(&shadow_ray, Interval::new(0.0, sample.distance))
+            {
+                continue;
             }
-            return color_from_emission + color_from_scatter;
+            total += sample.irradiance * scattering_pdf;
         }
-        Color::new(0.0, 0.0, 0.0)
+        total
     }
 }
 
@@ -78,27 +316,61 @@ impl Camera {
             aspect_ratio,
             image_width,
             image_height: 0,
-            center: Point3::new(0.0, 0.0, 0.0),
-            pixel00_loc: Point3::new(0.0, 0.0, 0.0),
-            pixel_delta_u: Vec3::new(0.0, 0.0, 0.0),
-            pixel_delta_v: Vec3::new(0.0, 0.0, 0.0),
             samples_per_pixel: 1,
-            pixel_samples_scale: 1.0,
             sqrt_spp: 1,
             recip_sqrt_spp: 1.0,
             max_depth: 0,
+            filter: Filter::Box { radius: 0.5 },
+            filter_sampler: FilterSampler::new(&Filter::Box { radius: 0.5 }),
             background: Color::new(1.0, 1.0, 1.0),
+            environment: None,
+            lights: Vec::new(),
             vfov: 90.0,
             lookfrom: Point3::new(0.0, 0.0, 0.0),
             lookat: Point3::new(0.0, 0.0, -1.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
-            u: Vec3::new(1.0, 1.0, 0.0),
-            v: Vec3::new(0.0, 1.0, 0.0),
-            w: Vec3::new(0.0, 1.0, 1.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
-            defocus_disk_u: Vec3::new(0.0, 0.0, 0.0),
-            defocus_disk_v: Vec3::new(0.0, 0.0, 0.0),
+            shift_x: 0.0,
+            shift_y: 0.0,
+            tilt: 0.0,
+            swing: 0.0,
+            aperture: ApertureShape::Circle,
+            vignetting: 0.0,
+            chromatic_aberration: 0.0,
+            exposure: None,
+            exposure_compensation: 0.0,
+            sensor_height: 24.0,
+            meters_per_unit: 1.0,
+            film_scale: 1.0,
+            lens_angle: 0.0,
+            shutter_end: 1.0,
+            projection: Projection::Perspective,
+            interpupillary_distance: 0.064,
+            convergence_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            shutter_curve: ShutterCurve::Box,
+            lookfrom_close: None,
+            lookat_close: None,
+            vfov_close: None,
+            crop: None,
+            composite_onto: None,
+            tile_size: 16,
+            tile_order: TileOrder::Scanline,
+            frame: ViewFrame {
+                center: Point3::new(0.0, 0.0, 0.0),
+                pixel00_loc: Point3::new(0.0, 0.0, 0.0),
+                pixel_delta_u: Vec3::new(0.0, 0.0, 0.0),
+                pixel_delta_v: Vec3::new(0.0, 0.0, 0.0),
+                u: Vec3::new(1.0, 0.0, 0.0),
+                v: Vec3::new(0.0, 1.0, 0.0),
+                w: Vec3::new(0.0, 0.0, 1.0),
+                defocus_disk_u: Vec3::new(0.0, 0.0, 0.0),
+                defocus_disk_v: Vec3::new(0.0, 0.0, 0.0),
+                focus_normal: Vec3::new(0.0, 0.0, 1.0),
+            },
+            animated: false,
         };
         cam.initialize();
         cam
@@ -111,63 +383,266 @@ impl Camera {
         }
         //self.samples_per_pixel = 100;
         self.sqrt_spp = (self.samples_per_pixel as f64).sqrt() as u32;
-        self.pixel_samples_scale = 1.0 / (self.sqrt_spp as f64 * self.sqrt_spp as f64);
         self.recip_sqrt_spp = 1.0 / (self.sqrt_spp as f64);
-        self.center = self.lookfrom;
+        self.filter_sampler = FilterSampler::new(&self.filter);
+
+        self.film_scale = self.exposure_compensation.exp2();
+        self.lens_angle = self.defocus_angle;
+        self.shutter_end = self.shutter_close;
+        if let Some(exposure) = self.exposure {
+            self.shutter_end = self.shutter_open + exposure.shutter_speed;
+            // The entrance pupil is the focal length over the f-number.
+            let pupil_radius = self.focal_length() / exposure.f_number / 2.0;
+            self.lens_angle = 2.0 * (pupil_radius / self.focus_dist).atan().to_degrees();
+            self.film_scale *= exposure.film_scale();
+        }
+
+        self.frame = self.view_frame(self.lookfrom, self.lookat, self.vfov);
+        self.animated = self.lookfrom_close.is_some()
+            || self.lookat_close.is_some()
+            || self.vfov_close.is_some();
+    }
+
+    // Focal length in scene units, from vfov and the sensor height.
+    fn focal_length(&self) -> f64 {
+        let millimetres = self.sensor_height / (2.0 * degrees_to_radians(self.vfov / 2.0).tan());
+        millimetres / 1000.0 / self.meters_per_unit
+    }
 
-        let theta = degrees_to_radians(self.vfov);
+    fn view_frame(&self, lookfrom: Point3, lookat: Point3, vfov: f64) -> ViewFrame {
+        let center = lookfrom;
+
+        let theta = degrees_to_radians(vfov);
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h * self.focus_dist;
         let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
 
-        self.w = Vec3::unit_vector(self.lookfrom - self.lookat);
-        self.u = Vec3::unit_vector(Vec3::cross(self.vup, self.w));
-        self.v = Vec3::cross(self.w, self.u);
+        let w = Vec3::unit_vector(lookfrom - lookat);
+        let u = Vec3::unit_vector(Vec3::cross(self.vup, w));
+        let v = Vec3::cross(w, u);
 
-        let viewport_u = self.u * viewport_width;
-        let viewport_v = -self.v * viewport_height;
+        let viewport_u = u * viewport_width;
+        let viewport_v = -v * viewport_height;
 
-        self.pixel_delta_u = viewport_u / self.image_width as f64;
-        self.pixel_delta_v = viewport_v / self.image_height as f64;
+        let pixel_delta_u = viewport_u / self.image_width as f64;
+        let pixel_delta_v = viewport_v / self.image_height as f64;
 
+        let shift = u * (self.shift_x * viewport_width) + v * (self.shift_y * viewport_height);
         let viewport_upper_left =
-            self.center - self.w * self.focus_dist - viewport_u / 2.0 - viewport_v / 2.0;
-
-        self.pixel00_loc = viewport_upper_left + (self.pixel_delta_u + self.pixel_delta_v) * 0.5;
+            center - w * self.focus_dist - viewport_u / 2.0 - viewport_v / 2.0 + shift;
         //self.max_depth = 10;
 
-        let defocus_radius = self.focus_dist * degrees_to_radians(self.defocus_angle / 2.0).tan();
-        self.defocus_disk_u = self.u * defocus_radius;
-        self.defocus_disk_v = self.v * defocus_radius;
+        let defocus_radius = self.focus_dist * degrees_to_radians(self.lens_angle / 2.0).tan();
+
+        // The plane of focus holds the hinge line, f / sin(tilt) below the
+        // lens, and the focus point; likewise for swing to the left.
+        let focal_length = self.focal_length();
+        let hinge_slope =
+            |angle: f64| self.focus_dist * degrees_to_radians(angle).sin() / focal_length;
+        let focus_normal =
+            Vec3::unit_vector(u * hinge_slope(self.swing) + v * hinge_slope(self.tilt) + w);
+        ViewFrame {
+            center,
+            pixel00_loc: viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5,
+            pixel_delta_u,
+            pixel_delta_v,
+            u,
+            v,
+            w,
+            defocus_disk_u: u * defocus_radius,
+            defocus_disk_v: v * defocus_radius,
+            focus_normal,
+        }
     }
 
-    pub fn sample_square(&self) -> Vec3 {
-        Vec3::new(
-            rtweekend::random_double() - 0.5,
-            rtweekend::random_double() - 0.5,
-            0.0,
+    // The view at a given ray time, for cameras that move during the shutter.
+    fn frame_at(&self, time: f64) -> ViewFrame {
+        if !self.animated {
+            return self.frame;
+        }
+        let span = self.shutter_end - self.shutter_open;
+        let t = if span > 0.0 {
+            ((time - self.shutter_open) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let lerp = |a: Vec3, b: Option<Vec3>| b.map_or(a, |b| a * (1.0 - t) + b * t);
+        let vfov = self
+            .vfov_close
+            .map_or(self.vfov, |close| self.vfov * (1.0 - t) + close * t);
+        self.view_frame(
+            lerp(self.lookfrom, self.lookfrom_close),
+            lerp(self.lookat, self.lookat_close),
+            vfov,
         )
     }
 
-    pub fn defocus_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + self.defocus_disk_u * p.x() + self.defocus_disk_v * p.y()
+    // Sets focus_dist to the depth of whatever is seen through the centre
+    // of pixel (i, j).
+    pub fn autofocus(&mut self, world: &dyn Hittable, i: usize, j: usize) {
+        self.initialize();
+        let frame = self.frame;
+        let pixel_center =
+            frame.pixel00_loc + frame.pixel_delta_u * i as f64 + frame.pixel_delta_v * j as f64;
+        let r = Ray::new(frame.center, pixel_center - frame.center);
+
+        let mut rec = HitRecord::default();
+        if world.hit(&r, Interval::new(0.0, INFINITY), &mut rec) {
+            self.focus_dist = Vec3::dot(rec.p - frame.center, -frame.w);
+            self.initialize();
+        } else {
+            eprintln!("Autofocus: nothing under pixel ({}, {}).", i, j);
+        }
     }
 
-    pub fn get_ray(&self, i: usize, j: usize, s_i: u32, s_j: u32) -> Ray {
-        let offset = self.sample_square_stratified(s_i, s_j);
-        let pixel_sample = self.pixel00_loc
-            + self.pixel_delta_u * (i as f64 + offset.x())
-            + self.pixel_delta_v * (j as f64 + offset.y());
+    // None for pixels the projection does not cover, such as the corners
+    // of a fisheye image, and for rays the lens barrel blocks. The colour is
+    // a per-channel weight for the radiance the ray brings back.
+    // (film_x, film_y) is the sample position in pixels from the top left
+    // corner of the image.
+    pub fn get_ray(&self, eye: Eye, film_x: f64, film_y: f64) -> Option<(Ray, Color)> {
+        let ray_time = self.shutter_open
+            + (self.shutter_end - self.shutter_open)
+                * self.shutter_curve.sample(rtweekend::random_double());
+        let frame = self.frame_at(ray_time);
+        let eye_shift = eye.offset() * self.interpupillary_distance;
+
+        // Position in the image, 0 to 1 from the top left corner.
+        let mut x = film_x / self.image_width as f64;
+        let mut y = film_y / self.image_height as f64;
+
+        // Each sample carries one colour channel, imaged at its own scale.
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        if self.chromatic_aberration != 0.0 {
+            let channel = ((rtweekend::random_double() * 3.0) as usize).min(2);
+            let scale = 1.0 + self.chromatic_aberration * (1.0 - channel as f64);
+            x = 0.5 + (x - 0.5) * scale;
+            y = 0.5 + (y - 0.5) * scale;
+            weight = match channel {
+                0 => Color::new(3.0, 0.0, 0.0),
+                1 => Color::new(0.0, 3.0, 0.0),
+                _ => Color::new(0.0, 0.0, 3.0),
+            };
+        }
+
+        if self.projection != Projection::Perspective {
+            let (origin, direction) = self.project(&frame, x, y, eye_shift)?;
+            return Some((Ray::new_with_time(origin, direction, ray_time), weight));
+        }
+
+        let pixel_sample = frame.pixel00_loc
+            + frame.pixel_delta_u * (x * self.image_width as f64 - 0.5)
+            + frame.pixel_delta_v * (y * self.image_height as f64 - 0.5);
+
+        // Off-axis stereo: each eye looks through the same window on the
+        // convergence plane, then focuses where that line crosses the
+        // plane of focus.
+        let eye_center = frame.center + frame.u * eye_shift;
+        let target = frame.center
+            + (pixel_sample - frame.center) * (self.convergence_dist / self.focus_dist);
+        let chief = target - eye_center;
+        let focus_t = Vec3::dot(
+            frame.focus_normal,
+            frame.center - frame.w * self.focus_dist - eye_center,
+        ) / Vec3::dot(frame.focus_normal, chief);
 
-        let ray_origin = if (self.defocus_angle <= 0.0) {
-            self.center
+        let ray_origin = if self.lens_angle <= 0.0 {
+            eye_center
         } else {
-            self.defocus_sample()
+            let lens = self.aperture.sample();
+            if self.vignetting > 0.0 {
+                // Image position scaled so the corners sit at distance 1.
+                let half_diagonal = (self.aspect_ratio * self.aspect_ratio + 1.0).sqrt();
+                let barrel = Vec3::new(
+                    (2.0 * x - 1.0) * self.aspect_ratio,
+                    1.0 - 2.0 * y,
+                    0.0,
+                ) * (self.vignetting / half_diagonal);
+                if (lens - barrel).length_squared() > 1.0 {
+                    return None;
+                }
+            }
+            eye_center + frame.defocus_disk_u * lens.x() + frame.defocus_disk_v * lens.y()
         };
-        let ray_direction = pixel_sample - ray_origin;
-        let ray_time = rtweekend::random_double();
-        Ray::new_with_time(ray_origin, ray_direction, ray_time)
+        // Rays through the lens converge where the chief ray meets the plane
+        // of focus. A tilted plane may cross it behind the lens, and then
+        // they spread out as if from there; if the two never meet, the
+        // pixel is focused at infinity.
+        let ray_direction = if focus_t != 0.0 {
+            chief + (eye_center - ray_origin) / focus_t
+        } else {
+            chief
+        };
+        Some((Ray::new_with_time(ray_origin, ray_direction, ray_time), weight))
+    }
+
+    fn project(
+        &self,
+        frame: &ViewFrame,
+        x: f64,
+        y: f64,
+        eye_shift: f64,
+    ) -> Option<(Point3, Vec3)> {
+        let (u, v, w) = (frame.u, frame.v, frame.w);
+        let eye_center = frame.center + u * eye_shift;
+        match self.projection {
+            Projection::Perspective => None,
+            Projection::Orthographic { height } => {
+                let width = height * self.image_width as f64 / self.image_height as f64;
+                let origin = eye_center + u * ((x - 0.5) * width) - v * ((y - 0.5) * height);
+                Some((origin, -w))
+            }
+            Projection::Fisheye { mapping, fov } => {
+                let px = (x - 0.5) * self.image_width as f64;
+                let py = (0.5 - y) * self.image_height as f64;
+                let radius = self.image_width.min(self.image_height) as f64 / 2.0;
+                let r = (px * px + py * py).sqrt() / radius;
+                if r > 1.0 {
+                    return None;
+                }
+                let theta_max = degrees_to_radians(fov) / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * theta_max,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (r * (theta_max / 2.0).sin()).clamp(-1.0, 1.0).asin()
+                    }
+                };
+                let phi = py.atan2(px);
+                let direction = u * (theta.sin() * phi.cos()) + v * (theta.sin() * phi.sin())
+                    - w * theta.cos();
+                Some((eye_center, direction))
+            }
+            Projection::Equirectangular => {
+                let longitude = (x - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - y) * PI;
+                let direction = u * (latitude.cos() * longitude.sin())
+                    + v * latitude.sin()
+                    - w * (latitude.cos() * longitude.cos());
+                // Omni-directional stereo: the eyes sit on a circle and turn
+                // with the view, so every longitude gets correct parallax.
+                // The circle shrinks towards the poles, where the eyes would
+                // otherwise swap over.
+                let right = u * longitude.cos() + w * longitude.sin();
+                let origin = frame.center + right * (eye_shift * latitude.cos());
+                Some((origin, direction))
+            }
+            Projection::CubeMap => {
+                let column = ((x * 3.0) as usize).min(2);
+                let row = ((y * 2.0) as usize).min(1);
+                let a = 2.0 * (x * 3.0 - column as f64) - 1.0;
+                let b = 1.0 - 2.0 * (y * 2.0 - row as f64);
+                // Forward, right and up of each face.
+                let (forward, right, up) = match (row, column) {
+                    (0, 0) => (u, w, v),
+                    (0, 1) => (-u, -w, v),
+                    (0, _) => (v, u, w),
+                    (_, 0) => (-v, u, -w),
+                    (_, 1) => (-w, u, v),
+                    (_, _) => (w, -u, v),
+                };
+                Some((eye_center, forward + right * a + up * b))
+            }
+        }
     }
 
     fn sample_square_stratified(&self, s_i: u32, s_j: u32) -> Vec3 {
@@ -181,44 +656,165 @@ impl Camera {
         world: &dyn Hittable,
         writer: &mut W,
     ) -> io::Result<()> {
-        writeln!(
-            writer,
-            "P3\n{} {}\n255",
-            self.image_width, self.image_height
-        )?;
-
-        let rows: Vec<String> = (0..self.image_height)
-            .into_par_iter()
-            .map(|j| {
-                eprint!("\rScanlines remaining: {} ", j);
-                let mut row = String::new();
-
-                for i in 0..self.image_width {
-                    let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-
-                    for s_j in 0..self.sqrt_spp {
-                        for s_i in 0..self.sqrt_spp {
-                            let r = self.get_ray(i, j, s_i, s_j);
-                            pixel_color +=
-                                self.ray_color(&r, self.max_depth, world);
-                        }
-                    }
+        let region = self.region();
+        let pixels = self.render_pixels(world, Eye::Center);
+        let Some(path) = &self.composite_onto else {
+            return Self::write_image(writer, region.width(), region.height(), &pixels);
+        };
+
+        let (width, height, mut image) = read_image(path)?;
+        if (width, height) != (self.image_width, self.image_height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} is {}x{}, not {}x{}",
+                    path, width, height, self.image_width, self.image_height
+                ),
+            ));
+        }
+        Self::paste(&mut image, width, &region, &pixels);
+        Self::write_image(writer, width, height, &image)
+    }
+
+    // Both eyes packed into one image.
+    pub fn render_stereo<W: Write>(
+        &self,
+        world: &dyn Hittable,
+        writer: &mut W,
+        layout: StereoLayout,
+    ) -> io::Result<()> {
+        let (width, height) = (self.region().width(), self.region().height());
+        let left = self.render_pixels(world, Eye::Left);
+        let right = self.render_pixels(world, Eye::Right);
+        match layout {
+            StereoLayout::TopBottom => {
+                let pixels = [left, right].concat();
+                Self::write_image(writer, width, 2 * height, &pixels)
+            }
+            StereoLayout::SideBySide => {
+                let pixels: Vec<Color> = left
+                    .chunks(width)
+                    .zip(right.chunks(width))
+                    .flat_map(|(l, r)| l.iter().chain(r).copied())
+                    .collect();
+                Self::write_image(writer, 2 * width, height, &pixels)
+            }
+        }
+    }
+
+    // Both eyes as separate images.
+    pub fn render_stereo_pair<W: Write>(
+        &self,
+        world: &dyn Hittable,
+        left: &mut W,
+        right: &mut W,
+    ) -> io::Result<()> {
+        let (width, height) = (self.region().width(), self.region().height());
+        let pixels = self.render_pixels(world, Eye::Left);
+        Self::write_image(left, width, height, &pixels)?;
+        let pixels = self.render_pixels(world, Eye::Right);
+        Self::write_image(right, width, height, &pixels)
+    }
+
+    // The pixels to render: the crop region, or else the whole image.
+    fn region(&self) -> Tile {
+        match &self.crop {
+            Some(crop) => crop.bounds(self.image_width, self.image_height),
+            None => Tile {
+                x0: 0,
+                y0: 0,
+                x1: self.image_width,
+                y1: self.image_height,
+            },
+        }
+    }
 
-                    pixel_color *= self.pixel_samples_scale;
+    // Filtered linear colour of every pixel in the region, row by row from
+    // the top.
+    pub fn render_pixels(&self, world: &dyn Hittable, eye: Eye) -> Vec<Color> {
+        let region = self.region();
+        let tiles = region.split(self.tile_size, self.tile_order);
+        let remaining = AtomicUsize::new(tiles.len());
 
-                    let mut buf = Vec::new();
-                    write_color(&mut buf, &pixel_color).unwrap();
-                    row.push_str(&String::from_utf8(buf).unwrap());
+        // par_bridge hands tiles out in order as threads come free.
+        let rendered: Vec<(Tile, Vec<Color>)> = tiles
+            .into_iter()
+            .par_bridge()
+            .map(|tile| {
+                let mut pixels = Vec::with_capacity(tile.width() * tile.height());
+                for j in tile.y0..tile.y1 {
+                    for i in tile.x0..tile.x1 {
+                        pixels.push(self.render_pixel(world, eye, i, j));
+                    }
                 }
-                row
+                let left = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                eprint!("\rTiles remaining: {} ", left);
+                (tile, pixels)
             })
             .collect();
+        eprintln!("\rDone.");
 
-        for line in rows {
-            write!(writer, "{}", line)?;
+        let mut image = vec![Color::new(0.0, 0.0, 0.0); region.width() * region.height()];
+        for (tile, pixels) in rendered {
+            let local = Tile {
+                x0: tile.x0 - region.x0,
+                y0: tile.y0 - region.y0,
+                x1: tile.x1 - region.x0,
+                y1: tile.y1 - region.y0,
+            };
+            Self::paste(&mut image, region.width(), &local, &pixels);
         }
+        image
+    }
 
-        eprintln!("\rDone.");
+    // Each pixel is the weighted average of its samples, which keeps
+    // negative filter lobes from adding noise to flat areas.
+    fn render_pixel(&self, world: &dyn Hittable, eye: Eye, i: usize, j: usize) -> Color {
+        let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+        let mut weight_sum = 0.0;
+
+        for s_j in 0..self.sqrt_spp {
+            for s_i in 0..self.sqrt_spp {
+                let offset = self.sample_square_stratified(s_i, s_j);
+                let (dx, dy, filter_weight) = self
+                    .filter_sampler
+                    .sample(offset.x() + 0.5, offset.y() + 0.5);
+                // Rays the lens blocks still count towards the weight, so
+                // vignetting darkens the pixel.
+                weight_sum += filter_weight;
+                let film_x = i as f64 + 0.5 + dx;
+                let film_y = j as f64 + 0.5 + dy;
+                if let Some((r, weight)) = self.get_ray(eye, film_x, film_y) {
+                    pixel_color +=
+                        weight * filter_weight * self.ray_color(&r, self.max_depth, world);
+                }
+            }
+        }
+
+        if weight_sum != 0.0 {
+            pixel_color *= self.film_scale / weight_sum;
+        }
+        pixel_color
+    }
+
+    // Copies the rows of `pixels` into `image`, `width` wide, at `at`.
+    fn paste(image: &mut [Color], width: usize, at: &Tile, pixels: &[Color]) {
+        for (row, chunk) in pixels.chunks(at.width()).enumerate() {
+            let start = (at.y0 + row) * width + at.x0;
+            image[start..start + at.width()].copy_from_slice(chunk);
+        }
+    }
+
+    fn write_image<W: Write>(
+        writer: &mut W,
+        width: usize,
+        height: usize,
+        pixels: &[Color],
+    ) -> io::Result<()> {
+        writeln!(writer, "P3\n{} {}\n255", width, height)?;
+        for pixel_color in pixels {
+            write_color(writer, pixel_color)?;
+        }
         Ok(())
     }
 }
@@ -38,3 +38,18 @@ pub fn write_color<W: Write>(out: &mut W, pixel_color: &Color) -> io::Result<()>
 
     writeln!(out, "{} {} {}", r_byte, g_byte, b_byte)
 }
+
+// Reads back an image saved from write_color output, undoing the gamma.
+pub fn read_image(path: &str) -> io::Result<(usize, usize, Vec<Color>)> {
+    let image = image::open(path).map_err(io::Error::other)?.to_rgb8();
+    // The middle of each byte's range, so writing it again gives the same byte.
+    let to_linear = |byte: u8| {
+        let gamma = (byte as f64 + 0.5) / 256.0;
+        gamma * gamma
+    };
+    let pixels = image
+        .pixels()
+        .map(|p| Color::new(to_linear(p[0]), to_linear(p[1]), to_linear(p[2])))
+        .collect();
+    Ok((image.width() as usize, image.height() as usize, pixels))
+}
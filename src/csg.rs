@@ -0,0 +1,139 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::rtweekend::INFINITY;
+use std::sync::Arc;
+
+// Guards against open or degenerate children reporting endless hits.
+const MAX_CHILD_HITS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+// Boolean combination of two closed objects. Both children are traced along
+// the ray and their boundary crossings merged in order; the first crossing
+// that changes membership of the combined solid is the hit. Surfaces keep the
+// material of the child they come from, and the parts of `b` cut out of `a`
+// by a difference have their normals flipped. Csg nodes can be nested.
+pub struct Csg {
+    op: CsgOperation,
+    a: Arc<dyn Hittable>,
+    b: Arc<dyn Hittable>,
+    bbox: AABB,
+}
+
+impl Csg {
+    pub fn new(op: CsgOperation, a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        let (box_a, box_b) = (a.bounding_box(), b.bounding_box());
+        let bbox = match op {
+            CsgOperation::Union => AABB::from_boxes(box_a, box_b),
+            CsgOperation::Intersection => AABB {
+                x: box_a.x.intersection(&box_b.x),
+                y: box_a.y.intersection(&box_b.y),
+                z: box_a.z.intersection(&box_b.z),
+            },
+            CsgOperation::Difference => box_a,
+        };
+        Self { op, a, b, bbox }
+    }
+
+    pub fn union(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Union, a, b)
+    }
+
+    pub fn intersection(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Intersection, a, b)
+    }
+
+    pub fn difference(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Difference, a, b)
+    }
+
+    // Every crossing of `object` beyond `t_min`, nearest first.
+    fn collect_hits(object: &dyn Hittable, r: &Ray, t_min: f64) -> Vec<HitRecord> {
+        let mut hits = Vec::new();
+        let mut ray_t = Interval::new(t_min, INFINITY);
+        let mut rec = HitRecord::default();
+        while hits.len() < MAX_CHILD_HITS && object.hit(r, ray_t, &mut rec) {
+            // Some primitives accept t == ray_t.min, so step past it.
+            ray_t.min = rec.t.next_up();
+            hits.push(rec.clone());
+        }
+        hits
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        // Crossings past ray_t.max are still needed to tell whether the ray
+        // starts inside a child, so gather them all and cut off afterwards.
+        let hits_a = Self::collect_hits(self.a.as_ref(), r, ray_t.min);
+        if hits_a.is_empty() && self.op != CsgOperation::Union {
+            return false;
+        }
+        let hits_b = Self::collect_hits(self.b.as_ref(), r, ray_t.min);
+
+        // A ray that leaves through a back face started inside.
+        let mut in_a = hits_a.first().is_some_and(|hit| !hit.front_face);
+        let mut in_b = hits_b.first().is_some_and(|hit| !hit.front_face);
+
+        let (mut i, mut j) = (0, 0);
+        while i < hits_a.len() || j < hits_b.len() {
+            let from_a = j >= hits_b.len() || (i < hits_a.len() && hits_a[i].t <= hits_b[j].t);
+            let hit = if from_a { &hits_a[i] } else { &hits_b[j] };
+            if hit.t >= ray_t.max {
+                return false;
+            }
+
+            let was_inside = self.op.contains(in_a, in_b);
+            // Front faces are entries. Using them rather than toggling keeps
+            // the state right when a crossing on a shared edge is skipped.
+            if from_a {
+                in_a = hit.front_face;
+                i += 1;
+            } else {
+                in_b = hit.front_face;
+                j += 1;
+            }
+
+            if self.op.contains(in_a, in_b) != was_inside {
+                let mut outward_normal = if hit.front_face {
+                    hit.normal
+                } else {
+                    -hit.normal
+                };
+                if self.op == CsgOperation::Difference && !from_a {
+                    outward_normal = -outward_normal;
+                }
+                *rec = hit.clone();
+                rec.set_face_normal(r, outward_normal);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
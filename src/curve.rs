@@ -0,0 +1,453 @@
+use crate::aabb::AABB;
+use crate::bvh::BVHOptions;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::linear_bvh::LinearBVH;
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::fs;
+use std::sync::Arc;
+
+// Recursion limit for the subdivision in `Curve::hit`.
+const MAX_SUBDIVISION: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveType {
+    // A ribbon that always faces the incoming ray; cheap, fine for thin hair.
+    Flat,
+    // A tube with proper normals around its circumference.
+    Cylinder,
+}
+
+struct CurveCommon {
+    cp: [Point3; 4],
+    width: [f64; 2],
+    kind: CurveType,
+    mat: Arc<dyn Material>,
+}
+
+// One piece of a cubic Bezier curve whose width varies linearly from one end
+// to the other. Intersection follows pbrt: the curve is taken into a frame
+// where the ray runs down +z from the origin, then split recursively until
+// each piece is close enough to a line segment to test against directly.
+pub struct Curve {
+    common: Arc<CurveCommon>,
+    u_min: f64,
+    u_max: f64,
+    // Control points of just this piece.
+    cp: [Point3; 4],
+    bbox: AABB,
+}
+
+struct CurveHit {
+    z: f64,
+    u: f64,
+    v: f64,
+    normal: Vec3,
+    tangent: Vec3,
+}
+
+impl Curve {
+    pub fn new(
+        cp: [Point3; 4],
+        width0: f64,
+        width1: f64,
+        kind: CurveType,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let common = Arc::new(CurveCommon {
+            cp,
+            width: [width0, width1],
+            kind,
+            mat,
+        });
+        Self::segment(common, 0.0, 1.0)
+    }
+
+    // Splits the curve into `segments` pieces so a BVH can bound them tightly.
+    pub fn new_segments(
+        cp: [Point3; 4],
+        width0: f64,
+        width1: f64,
+        kind: CurveType,
+        mat: Arc<dyn Material>,
+        segments: usize,
+    ) -> Vec<Arc<dyn Hittable>> {
+        let common = Arc::new(CurveCommon {
+            cp,
+            width: [width0, width1],
+            kind,
+            mat,
+        });
+        let n = segments.max(1);
+        (0..n)
+            .map(|i| {
+                let u_min = i as f64 / n as f64;
+                let u_max = (i + 1) as f64 / n as f64;
+                Arc::new(Self::segment(common.clone(), u_min, u_max)) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    fn segment(common: Arc<CurveCommon>, u_min: f64, u_max: f64) -> Self {
+        let c = &common.cp;
+        let cp = [
+            blossom_bezier(c, u_min, u_min, u_min),
+            blossom_bezier(c, u_min, u_min, u_max),
+            blossom_bezier(c, u_min, u_max, u_max),
+            blossom_bezier(c, u_max, u_max, u_max),
+        ];
+
+        let half_width = common.width[0].max(common.width[1]) / 2.0;
+        let pad = Vec3::new(half_width, half_width, half_width);
+        let mut bbox = AABB::empty();
+        for p in cp {
+            bbox = AABB::from_boxes(bbox, AABB::from_points(p - pad, p + pad));
+        }
+
+        Self {
+            common,
+            u_min,
+            u_max,
+            cp,
+            bbox,
+        }
+    }
+
+    fn width_at(&self, u: f64) -> f64 {
+        let [w0, w1] = self.common.width;
+        w0 + (w1 - w0) * u
+    }
+
+    fn recursive_intersect(
+        &self,
+        cp: &[Vec3; 4],
+        u0: f64,
+        u1: f64,
+        depth: i32,
+        // Depths along the ray still open; max shrinks as hits are found.
+        z_range: &mut Interval,
+        best: &mut Option<CurveHit>,
+    ) {
+        if depth > 0 {
+            let split = subdivide_bezier(cp);
+            let u_mid = (u0 + u1) / 2.0;
+            for (half, (a, b)) in [(0, (u0, u_mid)), (3, (u_mid, u1))] {
+                let sub = [
+                    split[half],
+                    split[half + 1],
+                    split[half + 2],
+                    split[half + 3],
+                ];
+                if self.overlaps_ray(&sub, a, b, *z_range) {
+                    self.recursive_intersect(&sub, a, b, depth - 1, z_range, best);
+                }
+            }
+            return;
+        }
+
+        // Reject rays that pass beyond either end of this piece.
+        let edge = (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        if edge < 0.0 {
+            return;
+        }
+        let edge = (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if edge < 0.0 {
+            return;
+        }
+
+        // Closest point to the ray on the chord, mapped back to the curve.
+        let seg = Vec3::new(cp[3].x - cp[0].x, cp[3].y - cp[0].y, 0.0);
+        let denom = seg.length_squared();
+        if denom == 0.0 {
+            return;
+        }
+        let w = ((-cp[0].x * seg.x - cp[0].y * seg.y) / denom).clamp(0.0, 1.0);
+        let u = (u0 + (u1 - u0) * w).clamp(u0, u1);
+        let hit_width = self.width_at(u);
+        let half_width = hit_width / 2.0;
+
+        let (pc, dpcdw) = eval_bezier(cp, w);
+        let dist_squared = pc.x * pc.x + pc.y * pc.y;
+        if dist_squared > half_width * half_width {
+            return;
+        }
+        // Hits closer than the curve is wide are the curve a ray just left.
+        if pc.z < hit_width || pc.z > z_range.max {
+            return;
+        }
+
+        let dist = dist_squared.sqrt();
+        let edge_func = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let v = if edge_func > 0.0 {
+            0.5 + dist / hit_width
+        } else {
+            0.5 - dist / hit_width
+        };
+
+        let tangent = Vec3::unit_vector(dpcdw);
+        let (z, normal) = match self.common.kind {
+            CurveType::Flat => (pc.z, Vec3::new(0.0, 0.0, -1.0)),
+            CurveType::Cylinder => {
+                let rise = (half_width * half_width - dist_squared).sqrt();
+                let n = Vec3::new(-pc.x, -pc.y, -rise);
+                let n = n - tangent * Vec3::dot(n, tangent);
+                (pc.z - rise, Vec3::unit_vector(n))
+            }
+        };
+        // Crossings before the start of the ray must not hide those after it.
+        if z <= z_range.min {
+            return;
+        }
+
+        z_range.max = pc.z;
+        *best = Some(CurveHit {
+            z,
+            u,
+            v,
+            normal,
+            tangent,
+        });
+    }
+
+    // Whether the ray, running down +z from the origin, can touch the piece.
+    fn overlaps_ray(&self, cp: &[Vec3; 4], u0: f64, u1: f64, z_range: Interval) -> bool {
+        let half_width = self.width_at(u0).max(self.width_at(u1)) / 2.0;
+        let mut lo = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut hi = Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in cp {
+            lo = Vec3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+            hi = Vec3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+        }
+        lo.x - half_width <= 0.0
+            && hi.x + half_width >= 0.0
+            && lo.y - half_width <= 0.0
+            && hi.y + half_width >= 0.0
+            && hi.z + half_width >= z_range.min
+            && lo.z - half_width <= z_range.max
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let length = r.direction().length();
+        if length == 0.0 {
+            return false;
+        }
+        let frame = ONB::new(r.direction());
+        let to_ray = |p: Point3| {
+            let q = p - r.origin();
+            Vec3::new(
+                Vec3::dot(q, frame.u()),
+                Vec3::dot(q, frame.v()),
+                Vec3::dot(q, frame.w()),
+            )
+        };
+        let cp = self.cp.map(to_ray);
+
+        let mut z_range = Interval::new(ray_t.min * length, ray_t.max * length);
+        if !self.overlaps_ray(&cp, self.u_min, self.u_max, z_range) {
+            return false;
+        }
+
+        // Enough subdivisions that each piece is within 5% of the width of a
+        // straight line.
+        let mut l0: f64 = 0.0;
+        for i in 0..2 {
+            let d = cp[i] - cp[i + 1] * 2.0 + cp[i + 2];
+            l0 = l0.max(d.x.abs()).max(d.y.abs()).max(d.z.abs());
+        }
+        let eps = self.common.width[0].max(self.common.width[1]) * 0.05;
+        let depth = if l0 > 0.0 && eps > 0.0 {
+            ((std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() / 2.0) as i32
+        } else {
+            0
+        };
+
+        let mut best = None;
+        self.recursive_intersect(
+            &cp,
+            self.u_min,
+            self.u_max,
+            depth.clamp(0, MAX_SUBDIVISION),
+            &mut z_range,
+            &mut best,
+        );
+        let Some(hit) = best else {
+            return false;
+        };
+
+        let t = hit.z / length;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = hit.u;
+        rec.v = hit.v;
+        rec.tangent = frame.transform(hit.tangent);
+        rec.mat = Some(Arc::clone(&self.common.mat));
+        rec.set_face_normal(r, frame.transform(hit.normal));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+fn lerp(t: f64, a: Vec3, b: Vec3) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+fn blossom_bezier(p: &[Vec3; 4], u0: f64, u1: f64, u2: f64) -> Vec3 {
+    let a = [
+        lerp(u0, p[0], p[1]),
+        lerp(u0, p[1], p[2]),
+        lerp(u0, p[2], p[3]),
+    ];
+    let b = [lerp(u1, a[0], a[1]), lerp(u1, a[1], a[2])];
+    lerp(u2, b[0], b[1])
+}
+
+// Control points of the two halves, sharing the middle point.
+fn subdivide_bezier(cp: &[Vec3; 4]) -> [Vec3; 7] {
+    [
+        cp[0],
+        (cp[0] + cp[1]) / 2.0,
+        (cp[0] + cp[1] * 2.0 + cp[2]) / 4.0,
+        (cp[0] + cp[1] * 3.0 + cp[2] * 3.0 + cp[3]) / 8.0,
+        (cp[1] + cp[2] * 2.0 + cp[3]) / 4.0,
+        (cp[2] + cp[3]) / 2.0,
+        cp[3],
+    ]
+}
+
+fn eval_bezier(cp: &[Vec3; 4], u: f64) -> (Vec3, Vec3) {
+    let cp1 = [
+        lerp(u, cp[0], cp[1]),
+        lerp(u, cp[1], cp[2]),
+        lerp(u, cp[2], cp[3]),
+    ];
+    let cp2 = [lerp(u, cp1[0], cp1[1]), lerp(u, cp1[1], cp1[2])];
+    let deriv = if (cp2[1] - cp2[0]).length_squared() > 0.0 {
+        (cp2[1] - cp2[0]) * 3.0
+    } else {
+        // Degenerate ends: fall back to the chord.
+        cp[3] - cp[0]
+    };
+    (lerp(u, cp2[0], cp2[1]), deriv)
+}
+
+// Loads strands from a plain text file: one strand per line as whitespace
+// separated "x y z" triples, with blank lines and lines starting with '#'
+// ignored. Each strand becomes a chain of Catmull-Rom style Bezier curves
+// tapering from `root_width` to `tip_width`, all placed in one BVH.
+pub fn load_hair(
+    path: &str,
+    scale: f64,
+    root_width: f64,
+    tip_width: f64,
+    kind: CurveType,
+    mat: Arc<dyn Material>,
+) -> HittableList {
+    let text = fs::read_to_string(path).expect("Failed to load hair file");
+
+    let mut curves: Vec<Arc<dyn Hittable>> = Vec::new();
+    let mut strand_count = 0;
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values: Vec<f64> = line
+            .split_whitespace()
+            .map(|v| {
+                v.parse()
+                    .unwrap_or_else(|_| panic!("{}:{}: bad number '{}'", path, line_number + 1, v))
+            })
+            .collect();
+        if values.len() % 3 != 0 || values.len() < 6 {
+            eprintln!(
+                "{}:{}: skipping strand without at least two whole points",
+                path,
+                line_number + 1
+            );
+            continue;
+        }
+
+        let points: Vec<Point3> = values
+            .chunks(3)
+            .map(|c| Point3::new(c[0], c[1], c[2]) * scale)
+            .collect();
+        curves.extend(strand_curves(&points, root_width, tip_width, kind, &mat));
+        strand_count += 1;
+    }
+
+    let bvh = LinearBVH::new(&curves, &BVHOptions::sah(4, 16));
+    eprintln!("Loaded {}: {} strands, {}", path, strand_count, bvh.stats());
+    HittableList::from(Arc::new(bvh))
+}
+
+// Bezier curves through every point of a polyline, with tangents taken from
+// the neighbouring points.
+pub fn strand_curves(
+    points: &[Point3],
+    root_width: f64,
+    tip_width: f64,
+    kind: CurveType,
+    mat: &Arc<dyn Material>,
+) -> Vec<Arc<dyn Hittable>> {
+    let n = points.len();
+    let at = |i: isize| points[i.clamp(0, n as isize - 1) as usize];
+    let width = |i: usize| root_width + (tip_width - root_width) * i as f64 / (n - 1) as f64;
+
+    (0..n - 1)
+        .map(|i| {
+            let k = i as isize;
+            let (p0, p1) = (at(k), at(k + 1));
+            let cp = [
+                p0,
+                p0 + (p1 - at(k - 1)) / 6.0,
+                p1 - (at(k + 2) - p0) / 6.0,
+                p1,
+            ];
+            Arc::new(Curve::new(cp, width(i), width(i + 1), kind, mat.clone())) as Arc<dyn Hittable>
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::EmptyMaterial;
+
+    // A hair that loops across the z axis, near z = 1.3 and again near 2.7.
+    fn loop_curve() -> Curve {
+        let cp = [
+            Point3::new(-1.0, 0.0, 1.0),
+            Point3::new(2.0, 0.0, 1.0),
+            Point3::new(2.0, 0.0, 3.0),
+            Point3::new(-1.0, 0.0, 3.0),
+        ];
+        Curve::new(cp, 0.1, 0.1, CurveType::Cylinder, Arc::new(EmptyMaterial))
+    }
+
+    #[test]
+    fn crossing_before_ray_start_does_not_hide_the_next() {
+        let curve = loop_curve();
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let mut rec = HitRecord::default();
+        assert!(curve.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+        assert!(rec.t < 2.0, "t = {}", rec.t);
+
+        assert!(curve.hit(&r, Interval::new(2.0, f64::INFINITY), &mut rec));
+        assert!(rec.t > 2.0 && rec.t < 3.0, "t = {}", rec.t);
+    }
+}
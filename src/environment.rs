@@ -0,0 +1,162 @@
+use crate::color::Color;
+use crate::pdf::Pdf;
+use crate::rtw_stb_image::RtwImage;
+use crate::rtweekend::random_double;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Light arriving from infinitely far away, for rays that miss the scene.
+pub trait Environment: Send + Sync {
+    fn radiance(&self, direction: Vec3) -> Color;
+    // Solid angle density of `generate`.
+    fn pdf_value(&self, direction: Vec3) -> f64;
+    fn generate(&self) -> Vec3;
+    // Whether camera rays that miss everything see it, or the camera
+    // background instead. It lights the scene either way.
+    fn visible(&self) -> bool {
+        true
+    }
+}
+
+// An environment read from an equirectangular
+// (longitude/latitude) image, usually an HDR. The middle of the image lies
+// along -z and its top is straight up.
+pub struct EnvironmentLight {
+    image: RtwImage,
+    width: usize,
+    height: usize,
+    // Turn about the y axis, in degrees.
+    pub rotation: f64,
+    pub intensity: f64,
+    pub visible: bool,
+    // Pixels are sampled by luminance times the solid angle they cover:
+    // first a row from the marginal, then a column from that row.
+    marginal_cdf: Vec<f64>,
+    conditional_cdf: Vec<f64>,
+    weights: Vec<f64>,
+    total_weight: f64,
+}
+
+impl EnvironmentLight {
+    pub fn new(filename: &str) -> Self {
+        let image = RtwImage::new(filename);
+        let (width, height) = (image.width().max(1), image.height().max(1));
+
+        let mut weights = Vec::with_capacity(width * height);
+        let mut conditional_cdf = Vec::with_capacity(width * height);
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total_weight = 0.0;
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            let mut row_total = 0.0;
+            for x in 0..width {
+                let [r, g, b] = image.float_pixel_data(x, y).map(|c| c.max(0.0) as f64);
+                let weight = (0.2126 * r + 0.7152 * g + 0.0722 * b) * sin_theta;
+                weights.push(weight);
+                row_total += weight;
+                conditional_cdf.push(row_total);
+            }
+            total_weight += row_total;
+            marginal_cdf.push(total_weight);
+        }
+        if total_weight <= 0.0 {
+            eprintln!("ERROR: Environment map '{}' is black.", filename);
+        }
+
+        Self {
+            image,
+            width,
+            height,
+            rotation: 0.0,
+            intensity: 1.0,
+            visible: true,
+            marginal_cdf,
+            conditional_cdf,
+            weights,
+            total_weight,
+        }
+    }
+
+    // The pixel a direction looks at, and the sine of its polar angle.
+    fn pixel(&self, direction: Vec3) -> (usize, usize, f64) {
+        let d = Vec3::unit_vector(direction);
+        let longitude = d.x.atan2(-d.z) - self.rotation.to_radians();
+        let latitude = d.y.clamp(-1.0, 1.0).asin();
+
+        let u = (longitude / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = 0.5 - latitude / PI;
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        (x, y, latitude.cos())
+    }
+}
+
+impl Environment for EnvironmentLight {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let (x, y, _) = self.pixel(direction);
+        let [r, g, b] = self.image.float_pixel_data(x, y);
+        Color::new(r as f64, g as f64, b as f64) * self.intensity
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        let (x, y, sin_theta) = self.pixel(direction);
+        if sin_theta <= 0.0 || self.total_weight <= 0.0 {
+            return 0.0;
+        }
+        // Density over the unit square of the image, then over the sphere.
+        let image_pdf = self.weights[y * self.width + x] * (self.width * self.height) as f64
+            / self.total_weight;
+        image_pdf / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if self.total_weight <= 0.0 {
+            return Vec3::random_unit_vector();
+        }
+        let target = random_double() * self.total_weight;
+        let y = self
+            .marginal_cdf
+            .partition_point(|&c| c <= target)
+            .min(self.height - 1);
+
+        let row = &self.conditional_cdf[y * self.width..(y + 1) * self.width];
+        let target = random_double() * row[self.width - 1];
+        let x = row.partition_point(|&c| c <= target).min(self.width - 1);
+
+        let u = (x as f64 + random_double()) / self.width as f64;
+        let v = (y as f64 + random_double()) / self.height as f64;
+        let longitude = (u - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let latitude = (0.5 - v) * PI;
+        Vec3::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        )
+    }
+
+    fn visible(&self) -> bool {
+        self.visible
+    }
+}
+
+// Directions towards the bright parts of an environment.
+pub struct EnvironmentPdf {
+    light: Arc<dyn Environment>,
+}
+
+impl EnvironmentPdf {
+    pub fn new(light: Arc<dyn Environment>) -> Self {
+        Self { light }
+    }
+}
+
+impl Pdf for EnvironmentPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.light.pdf_value(direction)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.light.generate()
+    }
+}
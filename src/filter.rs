@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+// Cells per axis in the table a FilterSampler draws offsets from.
+const TABLE_SIZE: usize = 64;
+
+// Pixel reconstruction filters. All are separable, so only the profile
+// along one axis is given; radii are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    // Shifted down so it reaches zero at the radius.
+    Gaussian { radius: f64, sigma: f64 },
+    // The cubic family of Mitchell and Netravali; b = c = 1/3 is their
+    // recommendation, with a radius of 2.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    // A sinc windowed by a sinc `tau` times wider.
+    Lanczos { radius: f64, tau: f64 },
+    // A four-term Blackman-Harris window spanning the radius.
+    BlackmanHarris { radius: f64 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. }
+            | Filter::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    pub fn evaluate(&self, x: f64) -> f64 {
+        let radius = self.radius();
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - x,
+            Filter::Gaussian { sigma, .. } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { b, c, .. } => {
+                let x = 2.0 * x / radius;
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+            Filter::BlackmanHarris { .. } => {
+                let t = 2.0 * PI * (x + radius) / (2.0 * radius);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Filter importance sampling: pixel offsets are drawn in proportion to the
+// filter's magnitude, tabulated along each axis, so every sample lands
+// with the same weight but for the sign of negative lobes. Nothing needs
+// splatting into neighbouring pixels.
+pub struct FilterSampler {
+    filter: Filter,
+    values: Vec<f64>,
+    // Running sum of |values|, ending at 1.
+    cdf: Vec<f64>,
+    // Integrals of |f| and f over the radius.
+    integral_abs: f64,
+    integral: f64,
+}
+
+impl FilterSampler {
+    pub fn new(filter: &Filter) -> Self {
+        let radius = filter.radius();
+        let cell = 2.0 * radius / TABLE_SIZE as f64;
+        let values: Vec<f64> = (0..TABLE_SIZE)
+            .map(|k| filter.evaluate(-radius + (k as f64 + 0.5) * cell))
+            .collect();
+
+        let mut cdf = Vec::with_capacity(TABLE_SIZE);
+        let mut total = 0.0;
+        for value in &values {
+            total += value.abs();
+            cdf.push(total);
+        }
+        let integral_abs = total * cell;
+        let integral = values.iter().sum::<f64>() * cell;
+        // Written so that NaN, from a bad radius, also fails.
+        if !(integral_abs > 0.0 && integral > 0.0) {
+            eprintln!(
+                "ERROR: Filter {:?} does not integrate to a positive value; using a box filter.",
+                filter
+            );
+            return Self::new(&Filter::Box { radius: 0.5 });
+        }
+        for c in cdf.iter_mut() {
+            *c /= total;
+        }
+
+        Self {
+            filter: *filter,
+            integral_abs,
+            integral,
+            values,
+            cdf,
+        }
+    }
+
+    // Offset from the pixel centre for uniform u, v in [0, 1), and a weight
+    // that averages to 1 over many samples.
+    pub fn sample(&self, u: f64, v: f64) -> (f64, f64, f64) {
+        let (x, weight_x) = self.sample_1d(u);
+        let (y, weight_y) = self.sample_1d(v);
+        (x, y, weight_x * weight_y)
+    }
+
+    fn sample_1d(&self, u: f64) -> (f64, f64) {
+        let radius = self.filter.radius();
+        let cell = 2.0 * radius / TABLE_SIZE as f64;
+
+        let k = self.cdf.partition_point(|&c| c <= u).min(TABLE_SIZE - 1);
+        let start = if k == 0 { 0.0 } else { self.cdf[k - 1] };
+        let width = self.cdf[k] - start;
+        let fraction = if width > 0.0 {
+            (u - start) / width
+        } else {
+            0.5
+        };
+        let x = -radius + (k as f64 + fraction) * cell;
+
+        let pdf = self.values[k].abs() / self.integral_abs;
+        if pdf <= 0.0 {
+            return (x, 0.0);
+        }
+        (x, self.filter.evaluate(x) / (pdf * self.integral))
+    }
+}
@@ -0,0 +1,199 @@
+use crate::bvh::BVHOptions;
+use crate::color::Color;
+use crate::hittable_list::HittableList;
+use crate::linear_bvh::LinearBVH;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::texture::SolidColor;
+use crate::transform::Matrix;
+use crate::triangle::Triangle;
+use crate::vec3::Point3;
+use gltf::mesh::Mode;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Picks the closest of our materials to a glTF metallic-roughness material.
+// Textures are not used, since triangles carry no texture coordinates.
+fn convert_material(material: &gltf::Material) -> Arc<dyn Material> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let base = Color::new(r as f64, g as f64, b as f64);
+
+    let strength = material.emissive_strength().unwrap_or(1.0);
+    let [er, eg, eb] = material.emissive_factor().map(|e| (e * strength) as f64);
+    if er.max(eg).max(eb) > 0.0 {
+        return Arc::new(DiffuseLight::new_from_color(Color::new(er, eg, eb)));
+    }
+
+    let transmission = material
+        .transmission()
+        .map_or(0.0, |t| t.transmission_factor());
+    if transmission >= 0.5 {
+        return Arc::new(Dielectric::new(material.ior().unwrap_or(1.5) as f64));
+    }
+
+    if pbr.metallic_factor() >= 0.5 {
+        Arc::new(Metal::new(base, pbr.roughness_factor() as f64))
+    } else {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(base))))
+    }
+}
+
+// glTF matrices are column-major 4x4.
+fn node_matrix(node: &gltf::Node) -> Matrix {
+    let m = node.transform().matrix();
+    let mut rows = [[0.0; 4]; 3];
+    for (i, row) in rows.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = m[j][i] as f64;
+        }
+    }
+    Matrix::from_rows(rows)
+}
+
+struct GltfContext<'a> {
+    path: &'a str,
+    scale: f64,
+    buffers: &'a [gltf::buffer::Data],
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    triangles: Vec<Triangle>,
+}
+
+impl GltfContext<'_> {
+    fn add_node(&mut self, node: &gltf::Node, parent: Matrix) {
+        let world = parent * node_matrix(node);
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if primitive.mode() != Mode::Triangles {
+                    eprintln!(
+                        "{}: skipping {:?} primitive in mesh {}",
+                        self.path,
+                        primitive.mode(),
+                        mesh.index()
+                    );
+                    continue;
+                }
+
+                let material = primitive.material();
+                let mat = self
+                    .materials
+                    .entry(material.index())
+                    .or_insert_with(|| convert_material(&material))
+                    .clone();
+
+                let buffers = self.buffers;
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+                let positions: Vec<Point3> = positions
+                    .map(|[x, y, z]| {
+                        world.point(Point3::new(x as f64, y as f64, z as f64)) * self.scale
+                    })
+                    .collect();
+                let indices: Vec<usize> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                    None => (0..positions.len()).collect(),
+                };
+
+                for tri in indices.chunks_exact(3) {
+                    if tri.iter().any(|&i| i >= positions.len()) {
+                        continue;
+                    }
+                    self.triangles.push(Triangle::new_with_points(
+                        positions[tri[0]],
+                        positions[tri[1]],
+                        positions[tri[2]],
+                        mat.clone(),
+                    ));
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.add_node(&child, world);
+        }
+    }
+}
+
+// Loads the default scene (or the first one) of a glTF 2.0 file, .gltf or
+// .glb, with node transforms applied. Buffers may be embedded as data URIs
+// or sit next to the file.
+pub fn load_gltf_model(path: &str, scale: f64) -> HittableList {
+    let mut object = HittableList::new();
+    for triangle in read_gltf(path, scale) {
+        object.add(Arc::new(triangle));
+    }
+
+    let bvh = LinearBVH::new(&object.objects, &BVHOptions::sah(4, 16));
+    eprintln!("Loaded {}: {}", path, bvh.stats());
+    HittableList::from(Arc::new(bvh))
+}
+
+fn read_gltf(path: &str, scale: f64) -> Vec<Triangle> {
+    let (document, buffers, _images) = gltf::import(path).expect("Failed to load glTF file");
+
+    let mut context = GltfContext {
+        path,
+        scale,
+        buffers: &buffers,
+        materials: HashMap::new(),
+        triangles: Vec::new(),
+    };
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next());
+    match scene {
+        Some(scene) => {
+            for node in scene.nodes() {
+                context.add_node(&node, Matrix::identity());
+            }
+        }
+        None => eprintln!("{}: glTF file has no scenes", path),
+    }
+    context.triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        assert!((a - b).length() < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn cubes_scene() {
+        let triangles = read_gltf("objects/cubes.gltf", 1.0);
+        // Four placed cubes of twelve triangles each.
+        assert_eq!(triangles.len(), 48);
+
+        // The gold cube is turned 45 degrees, so its corners reach sqrt(1/2)
+        // in z; the small red cube sits on top of it.
+        let bbox = Triangle::bounds(&triangles);
+        let half_diagonal = 0.5_f64.sqrt();
+        assert!((bbox.x.min + 2.0).abs() < 1e-6);
+        assert!((bbox.x.max - 1.9).abs() < 1e-6);
+        assert!(bbox.y.min.abs() < 1e-6);
+        assert!((bbox.y.max - 1.7).abs() < 1e-6);
+        assert!((bbox.z.min + half_diagonal).abs() < 1e-6);
+        assert!((bbox.z.max - half_diagonal).abs() < 1e-6);
+
+        let red = Color::new(0.8, 0.1, 0.1);
+        let gold = Color::new(1.0, 0.77, 0.34);
+        let glass = Color::new(1.0, 1.0, 1.0);
+        for triangle in &triangles {
+            let [p0, p1, p2] = triangle.vertices();
+            let centre = (p0 + p1 + p2) / 3.0;
+            let expected = if centre.x < -0.9 || centre.y > 1.2 {
+                red
+            } else if centre.x > 0.9 {
+                glass
+            } else {
+                gold
+            };
+            assert_close(triangle.albedo(), expected);
+        }
+    }
+}
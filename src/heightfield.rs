@@ -0,0 +1,314 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::perlin::Perlin;
+use crate::ray::Ray;
+use crate::rtw_stb_image::RtwImage;
+use crate::triangle::intersect_triangle;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// Terrain over a regular grid of height samples. `origin` is the corner of
+// the grid at height zero and `size` the extent along x and z, with `size.y`
+// the height of a sample of 1. Each cell is split into two triangles on the
+// fly; rays walk the cells with a 2D DDA and skip any cell whose height range
+// they pass above or below.
+pub struct Heightfield {
+    origin: Point3,
+    size: Vec3,
+    nx: usize,
+    nz: usize,
+    // World-space heights, row-major in z.
+    heights: Vec<f64>,
+    // Lowest and highest sample of every cell.
+    cell_bounds: Vec<Interval>,
+    mat: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Heightfield {
+    // `samples` holds `nx * nz` values in row-major order along z.
+    pub fn new(
+        origin: Point3,
+        size: Vec3,
+        nx: usize,
+        nz: usize,
+        samples: &[f64],
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        assert!(nx >= 2 && nz >= 2, "a heightfield needs at least 2x2 samples");
+        assert_eq!(samples.len(), nx * nz, "heightfield sample count mismatch");
+
+        let heights: Vec<f64> = samples.iter().map(|h| origin.y + h * size.y).collect();
+
+        let mut cell_bounds = Vec::with_capacity((nx - 1) * (nz - 1));
+        let mut y_range = Interval::empty();
+        for iz in 0..nz - 1 {
+            for ix in 0..nx - 1 {
+                let corners = [
+                    heights[iz * nx + ix],
+                    heights[iz * nx + ix + 1],
+                    heights[(iz + 1) * nx + ix],
+                    heights[(iz + 1) * nx + ix + 1],
+                ];
+                let bounds = Interval::new(
+                    corners.into_iter().fold(f64::INFINITY, f64::min),
+                    corners.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                );
+                y_range = y_range.union(&bounds);
+                cell_bounds.push(bounds);
+            }
+        }
+
+        let mut bbox = AABB::from_points(
+            Point3::new(origin.x, y_range.min, origin.z),
+            Point3::new(origin.x + size.x, y_range.max, origin.z + size.z),
+        );
+        bbox.pad_to_minimums();
+
+        Self {
+            origin,
+            size,
+            nx,
+            nz,
+            heights,
+            cell_bounds,
+            mat,
+            bbox,
+        }
+    }
+
+    // Heights from the grey level of an image, one sample per pixel.
+    pub fn from_image(filename: &str, origin: Point3, size: Vec3, mat: Arc<dyn Material>) -> Self {
+        let image = RtwImage::new(filename);
+        let (nx, nz) = (image.width(), image.height());
+        if nx < 2 || nz < 2 {
+            eprintln!(
+                "ERROR: Heightmap '{}' is missing or too small; using a flat field.",
+                filename
+            );
+            return Self::new(origin, size, 2, 2, &[0.0; 4], mat);
+        }
+
+        let mut samples = Vec::with_capacity(nx * nz);
+        for z in 0..nz {
+            for x in 0..nx {
+                let pixel = image.pixel_data(x, z);
+                // The stored grey level is the height, with no gamma applied.
+                let grey = (pixel[0] as f64 + pixel[1] as f64 + pixel[2] as f64) / (3.0 * 255.0);
+                samples.push(grey);
+            }
+        }
+        Self::new(origin, size, nx, nz, &samples, mat)
+    }
+
+    // Heights from Perlin turbulence, normalised to [0, 1].
+    pub fn from_perlin(
+        noise: &Perlin,
+        frequency: f64,
+        octaves: usize,
+        resolution: usize,
+        origin: Point3,
+        size: Vec3,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let n = resolution.max(2);
+        let mut samples = Vec::with_capacity(n * n);
+        for z in 0..n {
+            for x in 0..n {
+                let p = Point3::new(
+                    x as f64 / (n - 1) as f64 * size.x * frequency,
+                    0.0,
+                    z as f64 / (n - 1) as f64 * size.z * frequency,
+                );
+                samples.push(noise.turb(p, octaves));
+            }
+        }
+
+        let peak = samples.iter().cloned().fold(0.0, f64::max);
+        if peak > 0.0 {
+            samples.iter_mut().for_each(|h| *h /= peak);
+        }
+        Self::new(origin, size, n, n, &samples, mat)
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            self.size.x / (self.nx - 1) as f64,
+            self.size.z / (self.nz - 1) as f64,
+        )
+    }
+
+    fn vertex(&self, ix: usize, iz: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        Point3::new(
+            self.origin.x + ix as f64 * dx,
+            self.heights[iz * self.nx + ix],
+            self.origin.z + iz as f64 * dz,
+        )
+    }
+
+    // Smooth normal at a sample from central differences of its neighbours.
+    fn vertex_normal(&self, ix: usize, iz: usize) -> Vec3 {
+        let (dx, dz) = self.cell_size();
+        let (x0, x1) = (ix.saturating_sub(1), (ix + 1).min(self.nx - 1));
+        let (z0, z1) = (iz.saturating_sub(1), (iz + 1).min(self.nz - 1));
+        let slope_x = (self.heights[iz * self.nx + x1] - self.heights[iz * self.nx + x0])
+            / ((x1 - x0) as f64 * dx);
+        let slope_z = (self.heights[z1 * self.nx + ix] - self.heights[z0 * self.nx + ix])
+            / ((z1 - z0) as f64 * dz);
+        Vec3::unit_vector(Vec3::new(-slope_x, 1.0, -slope_z))
+    }
+
+    fn hit_cell(
+        &self,
+        ix: usize,
+        iz: usize,
+        r: &Ray,
+        ray_t: Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let corners = [(ix, iz), (ix + 1, iz), (ix + 1, iz + 1), (ix, iz + 1)];
+        let mut closest = ray_t.max;
+        let mut found = None;
+        for tri in [[0, 1, 2], [0, 2, 3]] {
+            let ids = tri.map(|k| corners[k]);
+            let vertices = ids.map(|(x, z)| self.vertex(x, z));
+            if let Some((t, weights)) =
+                intersect_triangle(&vertices, r, Interval::new(ray_t.min, closest))
+            {
+                closest = t;
+                found = Some((t, ids, vertices, weights));
+            }
+        }
+
+        let Some((t, ids, vertices, weights)) = found else {
+            return false;
+        };
+
+        let mut p = Point3::new(0.0, 0.0, 0.0);
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        for k in 0..3 {
+            p += vertices[k] * weights[k];
+            normal += self.vertex_normal(ids[k].0, ids[k].1) * weights[k];
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = (p.x - self.origin.x) / self.size.x;
+        rec.v = (p.z - self.origin.z) / self.size.z;
+        rec.mat = Some(Arc::clone(&self.mat));
+        rec.set_face_normal(r, Vec3::unit_vector(normal));
+        true
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some(span) = self.bbox.clip(r, ray_t) else {
+            return false;
+        };
+
+        let o = r.origin();
+        let d = r.direction();
+        let (dx, dz) = self.cell_size();
+        let (cells_x, cells_z) = (self.nx - 1, self.nz - 1);
+
+        let start = r.at(span.min);
+        let cell = |p: f64, origin: f64, step: f64, cells: usize| {
+            (((p - origin) / step).floor().max(0.0) as usize).min(cells - 1)
+        };
+        let mut ix = cell(start.x, self.origin.x, dx, cells_x);
+        let mut iz = cell(start.z, self.origin.z, dz, cells_z);
+
+        // Distance along the ray to the next cell boundary on each axis.
+        let next_boundary = |i: usize, o: f64, d: f64, origin: f64, step: f64| {
+            if d > 0.0 {
+                (origin + (i + 1) as f64 * step - o) / d
+            } else if d < 0.0 {
+                (origin + i as f64 * step - o) / d
+            } else {
+                f64::INFINITY
+            }
+        };
+        let mut t_next_x = next_boundary(ix, o.x, d.x, self.origin.x, dx);
+        let mut t_next_z = next_boundary(iz, o.z, d.z, self.origin.z, dz);
+        let t_delta_x = if d.x != 0.0 { dx / d.x.abs() } else { f64::INFINITY };
+        let t_delta_z = if d.z != 0.0 { dz / d.z.abs() } else { f64::INFINITY };
+
+        let mut t_enter = span.min;
+        loop {
+            let t_exit = t_next_x.min(t_next_z).min(span.max);
+
+            let y_enter = o.y + t_enter * d.y;
+            let y_exit = o.y + t_exit * d.y;
+            let bounds = self.cell_bounds[iz * cells_x + ix];
+            if y_enter.min(y_exit) <= bounds.max
+                && y_enter.max(y_exit) >= bounds.min
+                && self.hit_cell(ix, iz, r, ray_t, rec)
+            {
+                return true;
+            }
+
+            if t_exit >= span.max {
+                return false;
+            }
+            if t_next_x < t_next_z {
+                if (d.x > 0.0 && ix + 1 >= cells_x) || (d.x < 0.0 && ix == 0) {
+                    return false;
+                }
+                ix = if d.x > 0.0 { ix + 1 } else { ix - 1 };
+                t_enter = t_next_x;
+                t_next_x += t_delta_x;
+            } else {
+                if (d.z > 0.0 && iz + 1 >= cells_z) || (d.z < 0.0 && iz == 0) {
+                    return false;
+                }
+                iz = if d.z > 0.0 { iz + 1 } else { iz - 1 };
+                t_enter = t_next_z;
+                t_next_z += t_delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::EmptyMaterial;
+    use std::fs;
+
+    #[test]
+    fn ramp_image_gives_linear_heights() {
+        // Six columns from black to white, two rows, as a binary PPM.
+        let mut ppm = b"P6\n6 2\n255\n".to_vec();
+        for _ in 0..2 {
+            for level in [0u8, 51, 102, 153, 204, 255] {
+                ppm.extend([level; 3]);
+            }
+        }
+        let path = std::env::temp_dir().join("heightfield_ramp.ppm");
+        fs::write(&path, ppm).expect("Failed to write ramp image");
+
+        let field = Heightfield::from_image(
+            path.to_str().unwrap(),
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(5.0, 2.0, 1.0),
+            Arc::new(EmptyMaterial),
+        );
+        fs::remove_file(&path).ok();
+
+        assert_eq!((field.nx, field.nz), (6, 2));
+        for (x, &height) in field.heights[..6].iter().enumerate() {
+            let expected = 1.0 + 2.0 * x as f64 / 5.0;
+            let error = (height - expected).abs();
+            assert!(error < 1e-9, "{} != {}", height, expected);
+        }
+        assert_eq!(field.heights[..6], field.heights[6..]);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::aabb::AABB;
 use crate::interval::Interval;
 use crate::material::Material;
-use crate::ray::Ray;
+use crate::ray::{Ray, offset_ray_origin};
 use crate::vec3::{Point3, Vec3};
 use std::sync::Arc;
 
@@ -14,6 +14,8 @@ pub struct HitRecord {
     pub front_face: bool,
     pub u: f64,
     pub v: f64,
+    // Direction of increasing u along curves and hair; zero on other surfaces.
+    pub tangent: Vec3,
 }
 impl HitRecord {
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
@@ -24,6 +26,16 @@ impl HitRecord {
             -outward_normal
         };
     }
+
+    // Starts a secondary ray at this hit, offset so it cannot re-hit the same
+    // surface through rounding error.
+    pub fn spawn_ray(&self, direction: Vec3, time: f64) -> Ray {
+        Ray::new_with_time(
+            offset_ray_origin(self.p, self.normal, direction),
+            direction,
+            time,
+        )
+    }
 }
 
 impl Default for HitRecord {
@@ -36,12 +48,24 @@ impl Default for HitRecord {
             front_face: true,
             u: 0.0,
             v: 0.0,
+            tangent: Vec3::zero(),
         }
     }
 }
 pub trait Hittable : Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
     fn bounding_box(&self) -> AABB;
+    // Any-hit query for shadow rays; implementations may stop at the first hit.
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        let mut rec = HitRecord::default();
+        self.hit(r, ray_t, &mut rec)
+    }
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+    fn random(&self, _origin: Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
 
 pub struct Translate {
@@ -156,8 +180,15 @@ impl Hittable for RotateY {
             -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z,
         );
 
+        let tangent = Vec3::new(
+            self.cos_theta * rec.tangent.x + self.sin_theta * rec.tangent.z,
+            rec.tangent.y,
+            -self.sin_theta * rec.tangent.x + self.cos_theta * rec.tangent.z,
+        );
+
         rec.p = p;
         rec.normal = normal;
+        rec.tangent = tangent;
 
         true
     }
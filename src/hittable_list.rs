@@ -55,6 +55,9 @@ impl Hittable for HittableList {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.objects.iter().any(|object| object.occluded(r, ray_t))
+    }
     fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
         if self.objects.is_empty() {
             return 0.0;
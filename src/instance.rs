@@ -0,0 +1,81 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// A placement of shared geometry. The object (typically a mesh BVH) is kept
+// behind an `Arc`, so any number of instances cost one transform each.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    mat: Option<Arc<dyn Material>>,
+    bbox: AABB,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bbox = transform.bounding_box(&object.bounding_box());
+        Self {
+            object,
+            transform,
+            mat: None,
+            bbox,
+        }
+    }
+
+    pub fn new_with_material(
+        object: Arc<dyn Hittable>,
+        transform: Transform,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let mut instance = Self::new(object, transform);
+        instance.mat = Some(mat);
+        instance
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // The direction is not renormalised, so `t` means the same in both spaces.
+        let local_r = Ray::new_with_time(
+            self.transform.inverse_point(r.origin()),
+            self.transform.inverse_vector(r.direction()),
+            r.time(),
+        );
+
+        if !self.object.hit(&local_r, ray_t, rec) {
+            return false;
+        }
+
+        rec.p = self.transform.point(rec.p);
+        rec.normal = Vec3::unit_vector(self.transform.normal(rec.normal));
+        rec.tangent = self.transform.vector(rec.tangent);
+        if self.mat.is_some() {
+            rec.mat = self.mat.clone();
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    // Solid-angle densities are preserved by rigid motions and uniform scales,
+    // which covers the transforms lights are normally placed with.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(
+            self.transform.inverse_point(origin),
+            self.transform.inverse_vector(direction),
+        )
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let local = self.object.random(self.transform.inverse_point(origin));
+        self.transform.vector(local)
+    }
+}
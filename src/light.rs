@@ -0,0 +1,136 @@
+use crate::color::Color;
+use crate::rtweekend::INFINITY;
+use crate::vec3::{Point3, Vec3};
+
+// What a delta light sends towards a point: the unit direction to the
+// light, how far away it is, and the irradiance it gives a surface facing
+// it there.
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f64,
+    pub irradiance: Color,
+}
+
+// Lights with no size, which no ray can hit by chance. The camera reaches
+// them only by shadow rays.
+pub trait Light: Send + Sync {
+    fn sample(&self, p: Point3) -> Option<LightSample>;
+}
+
+// Shines equally in every direction from a point; intensity is per
+// steradian.
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        })
+    }
+}
+
+// A point light aimed at `target`, at full intensity within `falloff_start`
+// degrees of its axis and fading smoothly to nothing at `cone_angle`.
+pub struct SpotLight {
+    position: Point3,
+    axis: Vec3,
+    intensity: Color,
+    cos_cone_angle: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Color,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position,
+            axis: Vec3::unit_vector(target - position),
+            intensity,
+            cos_cone_angle: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(cone_angle).to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_cone_angle {
+            return 0.0;
+        }
+        let t = (cos_theta - self.cos_cone_angle) / (self.cos_falloff_start - self.cos_cone_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(Vec3::dot(-direction, self.axis));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: self.intensity * falloff / distance_squared,
+        })
+    }
+}
+
+// Parallel light from infinitely far away, travelling along `direction`,
+// like the sun or moon.
+pub struct DirectionalLight {
+    to_light: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            to_light: -Vec3::unit_vector(direction),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.to_light,
+            distance: INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
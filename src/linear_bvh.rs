@@ -0,0 +1,285 @@
+use crate::aabb::AABB;
+use crate::bvh::{
+    BVHOptions, BVHStats, BuildNode, BuildPrim, INTERSECTION_COST, MAX_SAH_DEPTH, TRAVERSAL_COST,
+};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::rtweekend;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// Deep enough for MAX_SAH_DEPTH plus the halving splits below it.
+const STACK_SIZE: usize = MAX_SAH_DEPTH + 64;
+const DEFAULT_REBUILD_RATIO: f64 = 1.5;
+
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bbox: AABB,
+    // First primitive for leaves, index of the second child for interior nodes.
+    // The first child always directly follows its parent.
+    offset: u32,
+    prim_count: u16,
+    axis: u8,
+}
+
+// A BVH flattened into one depth-first node array, with leaves referring to
+// contiguous ranges of `primitives`.
+//
+// For animation the primitives can be swapped out, or edited in place when
+// they are of one concrete type such as `Triangle`, and the bounds refitted
+// without touching the topology. `update` rebuilds from scratch once the
+// refitted SAH cost exceeds `rebuild_ratio` times the cost of the last build.
+pub struct LinearBVH<T: Hittable + ?Sized = dyn Hittable> {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<Arc<T>>,
+    // Position in `primitives` of each object, by its index in the input slice.
+    slots: Vec<usize>,
+    bbox: AABB,
+    stats: BVHStats,
+    options: BVHOptions,
+    build_cost: f64,
+    pub rebuild_ratio: f64,
+}
+
+impl<T: Hittable + ?Sized> LinearBVH<T> {
+    pub fn new(objects: &[Arc<T>], options: &BVHOptions) -> Self {
+        let mut prims = BuildPrim::from_objects(objects, options);
+        let root = BuildNode::build(&mut prims, options);
+
+        let primitives = prims
+            .iter()
+            .map(|prim| objects[prim.index].clone())
+            .collect();
+        let mut slots = vec![0; prims.len()];
+        for (slot, prim) in prims.iter().enumerate() {
+            slots[prim.index] = slot;
+        }
+        let mut nodes = Vec::with_capacity(2 * objects.len());
+        if !objects.is_empty() {
+            Self::flatten(&root, &mut nodes);
+        }
+
+        let stats = root.stats();
+        Self {
+            nodes,
+            primitives,
+            slots,
+            bbox: root.bbox(),
+            stats,
+            options: *options,
+            build_cost: stats.sah_cost,
+            rebuild_ratio: DEFAULT_REBUILD_RATIO,
+        }
+    }
+
+    fn flatten(node: &BuildNode, nodes: &mut Vec<LinearNode>) -> usize {
+        let index = nodes.len();
+        match node {
+            BuildNode::Leaf { bbox, start, count } => {
+                nodes.push(LinearNode {
+                    bbox: *bbox,
+                    offset: *start as u32,
+                    prim_count: *count as u16,
+                    axis: 0,
+                });
+            }
+            BuildNode::Interior {
+                bbox,
+                axis,
+                left,
+                right,
+            } => {
+                nodes.push(LinearNode {
+                    bbox: *bbox,
+                    offset: 0,
+                    prim_count: 0,
+                    axis: *axis as u8,
+                });
+                Self::flatten(left, nodes);
+                let second = Self::flatten(right, nodes);
+                nodes[index].offset = second as u32;
+            }
+        }
+        index
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        self.stats
+    }
+
+    pub fn set_primitives(&mut self, objects: &[Arc<T>]) {
+        assert_eq!(
+            objects.len(),
+            self.primitives.len(),
+            "LinearBVH::set_primitives needs one object per primitive"
+        );
+        for (index, object) in objects.iter().enumerate() {
+            self.primitives[self.slots[index]] = object.clone();
+        }
+    }
+
+    // Recomputes every box bottom-up from the current primitives. Children are
+    // stored after their parent, so one reverse sweep visits them first.
+    pub fn refit(&mut self) {
+        let mut costs = vec![0.0; self.nodes.len()];
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            if node.prim_count > 0 {
+                let start = node.offset as usize;
+                let mut bbox = AABB::empty();
+                for object in &self.primitives[start..start + node.prim_count as usize] {
+                    bbox = AABB::from_boxes(bbox, object.bounding_box());
+                }
+                self.nodes[i].bbox = bbox;
+                costs[i] = INTERSECTION_COST * node.prim_count as f64;
+            } else {
+                let (left, right) = (i + 1, node.offset as usize);
+                let (left_box, right_box) = (self.nodes[left].bbox, self.nodes[right].bbox);
+                let bbox = AABB::from_boxes(left_box, right_box);
+                let area = bbox.surface_area();
+                costs[i] = if area > 0.0 {
+                    TRAVERSAL_COST
+                        + (left_box.surface_area() * costs[left]
+                            + right_box.surface_area() * costs[right])
+                            / area
+                } else {
+                    TRAVERSAL_COST + costs[left] + costs[right]
+                };
+                self.nodes[i].bbox = bbox;
+            }
+        }
+
+        if let Some(root) = self.nodes.first() {
+            self.bbox = root.bbox;
+            self.stats.sah_cost = costs[0];
+        }
+    }
+
+    // Refits, then rebuilds if the tree has degraded too far. Returns whether
+    // a rebuild happened.
+    pub fn update(&mut self) -> bool {
+        self.refit();
+        if self.stats.sah_cost <= self.build_cost * self.rebuild_ratio {
+            return false;
+        }
+
+        let objects: Vec<Arc<T>> = self
+            .slots
+            .iter()
+            .map(|&slot| self.primitives[slot].clone())
+            .collect();
+
+        let rebuild_ratio = self.rebuild_ratio;
+        *self = LinearBVH::new(&objects, &self.options);
+        self.rebuild_ratio = rebuild_ratio;
+        true
+    }
+
+    // Visits nodes front to back and calls `visit` for each primitive whose
+    // leaf box is hit. Traversal stops as soon as `visit` returns true.
+    fn traverse<F>(&self, r: &Ray, ray_t: Interval, mut visit: F)
+    where
+        F: FnMut(&Arc<T>, &mut Interval) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let origin = r.origin();
+        let dir = r.direction();
+        let inv_dir = Vec3::new(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        let dir_is_neg = [inv_dir.x < 0.0, inv_dir.y < 0.0, inv_dir.z < 0.0];
+
+        let mut t = ray_t;
+        let mut stack = [0u32; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0usize;
+
+        loop {
+            let node = &self.nodes[current];
+            if node.bbox.hit_with_inverse(origin, inv_dir, t) {
+                if node.prim_count > 0 {
+                    let start = node.offset as usize;
+                    for object in &self.primitives[start..start + node.prim_count as usize] {
+                        if visit(object, &mut t) {
+                            return;
+                        }
+                    }
+                } else {
+                    // Descend into the near child first so `t` shrinks early.
+                    let (near, far) = if dir_is_neg[node.axis as usize] {
+                        (node.offset, current as u32 + 1)
+                    } else {
+                        (current as u32 + 1, node.offset)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near as usize;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len] as usize;
+        }
+    }
+}
+
+impl<T: Hittable + Clone> LinearBVH<T> {
+    // The primitive at `index` in the slice the BVH was built from, to be
+    // edited in place; it is copied first if something else still shares
+    // it. Call `refit` or `update` once all edits are done.
+    pub fn primitive_mut(&mut self, index: usize) -> &mut T {
+        Arc::make_mut(&mut self.primitives[self.slots[index]])
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for LinearBVH<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut hit_anything = false;
+        self.traverse(r, ray_t, |object, t| {
+            if object.hit(r, *t, rec) {
+                hit_anything = true;
+                t.max = rec.t;
+            }
+            false
+        });
+        hit_anything
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        let mut occluded = false;
+        self.traverse(r, ray_t, |object, t| {
+            occluded = object.occluded(r, *t);
+            occluded
+        });
+        occluded
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.primitives.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.primitives.len() as f64;
+        self.primitives
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        if self.primitives.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = rtweekend::random_int(0, self.primitives.len() as i32 - 1);
+        self.primitives[index as usize].random(origin)
+    }
+}
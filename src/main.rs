@@ -8,6 +8,7 @@ mod hittable_list;
 mod interval;
 mod material;
 mod onb;
+mod pdf;
 mod perlin;
 mod quad;
 mod ray;
@@ -18,22 +19,64 @@ mod texture;
 mod vec3;
 mod obj;
 mod triangle;
-
-use crate::bvh::BVHNode;
-use crate::camera::Camera;
+mod transform;
+mod instance;
+mod linear_bvh;
+mod csg;
+mod sdf;
+mod heightfield;
+mod poly;
+mod quadric;
+mod curve;
+mod ply;
+mod stl;
+mod gltf_model;
+mod quaternion;
+mod motion;
+mod aperture;
+mod filter;
+mod tile;
+mod environment;
+mod sky;
+mod light;
+
+use crate::aabb::AABB;
+use crate::aperture::{ApertureMask, ApertureShape};
+use crate::bvh::{BVHNode, BVHOptions};
+use crate::camera::{Camera, Exposure, FisheyeMapping, Projection, ShutterCurve, StereoLayout};
 use crate::constant_medium::ConstantMedium;
+use crate::csg::Csg;
+use crate::curve::{Curve, CurveType};
+use crate::environment::EnvironmentLight;
+use crate::filter::Filter;
+use crate::heightfield::Heightfield;
 use crate::hittable::{HitRecord, Hittable, RotateY, Translate};
+use crate::instance::Instance;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::linear_bvh::LinearBVH;
+use crate::motion::{Keyframe, MotionInstance};
 use crate::material::Dielectric;
+use crate::perlin::Perlin;
 use crate::quad::Quad;
+use crate::quadric::{Quadric, Torus};
+use crate::quaternion::Quaternion;
 use crate::rtweekend::{INFINITY, random_double};
+use crate::sky::PhysicalSky;
+use crate::sdf::{
+    BoxSdf, DifferenceSdf, FnSdf, IntersectionSdf, Mandelbulb, RepeatSdf, SdfObject,
+    SmoothUnionSdf, SphereSdf, TorusSdf, TwistSdf, UnionSdf,
+};
 use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture};
+use crate::tile::{CropRegion, TileOrder};
+use crate::transform::Transform;
+use crate::triangle::Triangle;
 use color::{Color, write_color};
 use console::style;
 use hittable_list::HittableList;
 use image::{ImageBuffer, RgbImage};
 use indicatif::ProgressBar;
 use interval::Interval;
-use material::{DiffuseLight, Lambertian, Material, Metal};
+use material::{DiffuseLight, KajiyaKay, Lambertian, Material, Metal};
 use ray::Ray;
 use sphere::Sphere;
 use std::f64::consts::PI;
@@ -342,166 +385,2196 @@ pub fn quads() -> io::Result<()> {
     Ok(())
 }
 
-pub fn simple_light() -> io::Result<()> {
-    let path = std::path::Path::new("output/book2/image18ppm");
+pub fn planar_shapes() -> io::Result<()> {
+    let path = std::path::Path::new("output/planar_shapes.ppm");
     let prefix = path.parent().unwrap();
     std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
 
-    let file = File::create("output/book2/image18.ppm").expect("Failed to create file");
+    let file = File::create("output/planar_shapes.ppm").expect("Failed to create file");
     let mut out = BufWriter::new(file);
+
     let mut world = HittableList::new();
 
-    let pertext = Arc::new(NoiseTexture::new(4.0));
+    let white = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.73, 0.73, 0.73,
+    )))));
+    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        1.0, 0.2, 0.2,
+    )))));
+    let blue = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.2, 0.2, 1.0,
+    )))));
+    let checker = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        0.25,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    let light = Arc::new(DiffuseLight::new_from_color(Color::new(6.0, 6.0, 6.0)));
 
-    world.add(Arc::new(Sphere::static_new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Some(Arc::new(Lambertian::new(pertext.clone()))),
+    world.add(Arc::new(Quad::new(
+        Point3::new(-5.0, -2.0, -5.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 10.0),
+        white,
     )));
-
-    world.add(Arc::new(Sphere::static_new(
-        Point3::new(0.0, 2.0, 0.0),
-        2.0,
-        Some(Arc::new(Lambertian::new(pertext.clone()))),
+    world.add(Arc::new(Quad::new_triangle(
+        Point3::new(-4.0, -2.0, 0.0),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(1.5, 3.0, 0.0),
+        red,
+    )));
+    world.add(Arc::new(Quad::new_ellipse(
+        Point3::new(2.5, 0.0, 0.0),
+        Vec3::new(1.5, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.5),
+        blue,
+    )));
+    world.add(Arc::new(Quad::new_annulus(
+        Point3::new(0.0, -1.99, 2.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.6,
+        1.5,
+        checker,
+    )));
+    world.add(Arc::new(Quad::new_disk(
+        Point3::new(0.0, 4.0, 1.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        1.5,
+        light,
     )));
 
-    let difflight = Arc::new(DiffuseLight::new_from_color(Color::new(4.0, 4.0, 4.0)));
+    let mut cam = Camera::new(1.0, 400);
+
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 60.0;
+    cam.lookfrom = Point3::new(0.0, 2.0, 9.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.1, 0.1, 0.15);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn quadrics() -> io::Result<()> {
+    let path = std::path::Path::new("output/quadrics.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/quadrics.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.8, 0.2, 0.2,
+    )))));
+    let blue = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.2, 0.3, 0.8,
+    )))));
+    let gold = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1));
+    let glass = Arc::new(Dielectric::new(1.5));
+    let light = Arc::new(DiffuseLight::new_from_color(Color::new(4.0, 4.0, 4.0)));
 
     world.add(Arc::new(Quad::new(
-        Point3::new(3.0, 1.0, -2.0),
-        Vec3::new(2.0, 0.0, 0.0),
-        Vec3::new(0.0, 2.0, 0.0),
-        difflight.clone(),
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        ground,
     )));
-
-    world.add(Arc::new(Sphere::static_new(
-        Point3::new(0.0, 7.0, 0.0),
+    world.add(Arc::new(Quadric::new_cylinder(
+        Point3::new(-4.5, 0.0, 0.0),
+        0.8,
         2.0,
-        Some(difflight),
+        true,
+        red,
+    )));
+    world.add(Arc::new(Quadric::new_cone(
+        Point3::new(-2.0, 0.0, 0.0),
+        0.9,
+        2.5,
+        true,
+        blue.clone(),
+    )));
+    world.add(Arc::new(Quadric::new_paraboloid(
+        Point3::new(0.5, 0.0, 0.0),
+        1.0,
+        2.0,
+        false,
+        gold,
+    )));
+    world.add(Arc::new(Quadric::new_hyperboloid(
+        Point3::new(3.0, 0.0, 0.0),
+        0.4,
+        0.9,
+        2.5,
+        false,
+        blue,
+    )));
+    world.add(Arc::new(Torus::new(
+        Point3::new(0.0, 0.3, 3.0),
+        1.2,
+        0.3,
+        glass,
+    )));
+    world.add(Arc::new(Quadric::new_cylinder(
+        Point3::new(0.0, 5.0, 0.0),
+        2.0,
+        0.2,
+        true,
+        light,
     )));
 
     let mut cam = Camera::new(16.0 / 9.0, 400);
+
     cam.aspect_ratio = 16.0 / 9.0;
     cam.image_width = 400;
-    cam.samples_per_pixel = 10;
+    cam.samples_per_pixel = 100;
     cam.max_depth = 50;
-    cam.background = Color::new(0.0, 0.0, 0.0);
 
-    cam.vfov = 20.0;
-    cam.lookfrom = Point3::new(26.0, 3.0, 6.0);
-    cam.lookat = Point3::new(0.0, 2.0, 0.0);
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 12.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
     cam.vup = Vec3::new(0.0, 1.0, 0.0);
 
+    cam.background = Color::new(0.3, 0.35, 0.45);
     cam.defocus_angle = 0.0;
 
     cam.initialize();
+
     cam.render(&world, &mut out)?;
     Ok(())
 }
 
-pub fn cornell_box() -> io::Result<()> {
-    let path = std::path::Path::new("output/final.ppm");
+pub fn csg_shapes() -> io::Result<()> {
+    let path = std::path::Path::new("output/csg.ppm");
     let prefix = path.parent().unwrap();
     std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
 
-    let file = File::create("output/final.ppm").expect("Failed to create file");
+    let file = File::create("output/csg.ppm").expect("Failed to create file");
     let mut out = BufWriter::new(file);
 
     let mut world = HittableList::new();
 
-    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
-        0.65, 0.05, 0.05,
-    )))));
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
     let white = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
         0.73, 0.73, 0.73,
     )))));
-    let green = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
-        0.12, 0.45, 0.15,
+    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.8, 0.2, 0.2,
     )))));
-    let light = Arc::new(DiffuseLight::new_from_color(Color::new(15.0, 15.0, 15.0)));
+    let steel = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.75), 0.2));
+    let glass = Arc::new(Dielectric::new(1.5));
 
     world.add(Arc::new(Quad::new(
-        Point3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        green.clone(),
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        ground,
     )));
 
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        red.clone(),
-    )));
+    // Biconvex lens: the overlap of two large spheres.
+    let lens = Csg::intersection(
+        Arc::new(Sphere::static_new(
+            Point3::new(-3.0, 1.5, -2.6),
+            3.0,
+            Some(glass.clone()),
+        )),
+        Arc::new(Sphere::static_new(
+            Point3::new(-3.0, 1.5, 2.6),
+            3.0,
+            Some(glass),
+        )),
+    );
+    world.add(Arc::new(lens));
+
+    // A cube hollowed out by a sphere, with red walls inside the cavity.
+    let hollow_box = Csg::difference(
+        quad::make_box(
+            Point3::new(-1.0, 0.0, -1.0),
+            Point3::new(1.0, 2.0, 1.0),
+            white,
+        ),
+        Arc::new(Sphere::static_new(
+            Point3::new(0.0, 1.0, 0.0),
+            1.3,
+            Some(red),
+        )),
+    );
+    world.add(Arc::new(hollow_box));
+
+    // A drilled hub: a disc with a through hole and a collar unioned on top.
+    let disc = Arc::new(Quadric::new_cylinder(
+        Point3::new(3.0, 0.0, 0.0),
+        1.2,
+        0.6,
+        true,
+        steel.clone(),
+    ));
+    let collar = Arc::new(Quadric::new_cylinder(
+        Point3::new(3.0, 0.6, 0.0),
+        0.6,
+        0.8,
+        true,
+        steel.clone(),
+    ));
+    let hole = Arc::new(Quadric::new_cylinder(
+        Point3::new(3.0, -0.1, 0.0),
+        0.35,
+        1.6,
+        true,
+        steel,
+    ));
+    let hub = Csg::difference(Arc::new(Csg::union(disc, collar)), hole);
+    world.add(Arc::new(hub));
 
-    world.add(Arc::new(Quad::new(
-        Point3::new(343.0, 554.0, 332.0),
-        Vec3::new(-130.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -105.0),
-        light.clone(),
-    )));
+    let mut cam = Camera::new(16.0 / 9.0, 400);
 
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 0.0, 0.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        white.clone(),
-    )));
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 5.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn sdf_shapes() -> io::Result<()> {
+    let path = std::path::Path::new("output/sdf.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/sdf.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    let orange = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.9, 0.5, 0.1,
+    )))));
+    let teal = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.1, 0.6, 0.6,
+    )))));
+    let steel = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.75), 0.1));
+    let bulb_mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.8, 0.3, 0.4,
+    )))));
 
     world.add(Arc::new(Quad::new(
-        Point3::new(555.0, 555.0, 555.0),
-        Vec3::new(-555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -555.0),
-        white.clone(),
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        ground,
     )));
 
-    world.add(Arc::new(Quad::new(
-        Point3::new(0.0, 0.0, 555.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        white.clone(),
+    let blob = SmoothUnionSdf::new(
+        Arc::new(SphereSdf::new(Point3::new(-4.3, 0.8, 0.0), 0.8)),
+        Arc::new(SphereSdf::new(Point3::new(-3.4, 1.5, 0.0), 0.6)),
+        0.6,
+    );
+    world.add(Arc::new(SdfObject::new(Arc::new(blob), orange)));
+
+    // Twisting happens about the y axis, so the column is built at the origin
+    // and moved into place afterwards.
+    let column = TwistSdf::new(
+        Arc::new(BoxSdf::new(
+            Point3::new(0.0, 1.2, 0.0),
+            Vec3::new(0.4, 1.2, 0.4),
+            0.05,
+        )),
+        1.2,
+    );
+    world.add(Arc::new(Translate::new(
+        Arc::new(SdfObject::new(Arc::new(column), teal.clone())),
+        Vec3::new(-1.5, 0.0, 0.0),
     )));
 
-    let box1 = quad::make_box(
-        Point3::new(0.0, 0.0, 0.0),
-        Point3::new(165.0, 330.0, 165.0),
-        white.clone(),
+    let bulb = SdfObject::new(
+        Arc::new(Mandelbulb::new(Point3::new(0.7, 1.3, 0.0), 1.1)),
+        bulb_mat,
     );
-    let box1 = Arc::new(RotateY::new(box1, 15.0));
-    let box1 = Arc::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0)));
-    world.add(box1);
+    world.add(Arc::new(bulb));
+
+    // Torus with a notch cut out, resting on a lens-shaped foot.
+    let notched = DifferenceSdf::new(
+        Arc::new(TorusSdf::new(Point3::new(3.3, 1.3, 0.0), 0.8, 0.25)),
+        Arc::new(BoxSdf::new(
+            Point3::new(3.3, 2.0, 0.0),
+            Vec3::new(0.3, 0.5, 0.5),
+            0.0,
+        )),
+    );
+    let foot = IntersectionSdf::new(
+        Arc::new(SphereSdf::new(Point3::new(3.3, -0.1, 0.0), 0.8)),
+        Arc::new(BoxSdf::new(
+            Point3::new(3.3, 0.3, 0.0),
+            Vec3::new(1.0, 0.3, 1.0),
+            0.0,
+        )),
+    );
+    let stand = UnionSdf::new(Arc::new(notched), Arc::new(foot));
+    world.add(Arc::new(SdfObject::new(Arc::new(stand), steel)));
 
-    /*let box2 = quad::make_box(
-        Point3::new(0.0, 0.0, 0.0),
-        Point3::new(165.0, 165.0, 165.0),
-        white.clone(),
+    let beads = RepeatSdf::new(
+        Arc::new(SphereSdf::new(Point3::new(0.0, 0.25, 3.0), 0.25)),
+        Vec3::new(0.8, 0.0, 0.0),
+        [4, 0, 0],
     );
-    let box2 = Arc::new(RotateY::new(box2, -18.0));
-    let box2 = Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0)));
-    world.add(box2);*/
-    let coffin = Arc::new(obj::load_obj_model("objects/coffin.obj", 100.0));
-    let coffin = Arc::new(Translate::new(coffin, Vec3::new(100.0, 0.0, 200.0)));
-    world.add(coffin);
+    world.add(Arc::new(SdfObject::new(Arc::new(beads), teal)));
+
+    // A rippled sphere; the ripple doubles the gradient at most, so halve it.
+    let ripple_center = Point3::new(-4.0, 0.6, 2.6);
+    let ripples = FnSdf::new(
+        AABB::from_points(
+            ripple_center - Vec3::new(0.7, 0.7, 0.7),
+            ripple_center + Vec3::new(0.7, 0.7, 0.7),
+        ),
+        move |p| {
+            let q = p - ripple_center;
+            let wave = 0.05 * (10.0 * q.x).sin() * (10.0 * q.y).sin() * (10.0 * q.z).sin();
+            (q.length() - 0.55 + wave) * 0.5
+        },
+    );
+    world.add(Arc::new(SdfObject::new(
+        Arc::new(ripples),
+        Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+    )));
 
-    let mut cam = Camera::new(1.0, 600);
+    let world = BVHNode::new_from_list(&mut world);
 
-    cam.aspect_ratio = 1.0;
-    cam.image_width = 600;
-    cam.samples_per_pixel = 1000;
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
     cam.max_depth = 50;
-    cam.background = Color::new(0.0, 0.0, 0.0);
 
     cam.vfov = 40.0;
-    cam.lookfrom = Point3::new(278.0, 278.0, -800.0);
-    cam.lookat = Point3::new(278.0, 278.0, 0.0);
+    cam.lookfrom = Point3::new(0.0, 4.0, 11.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn terrain() -> io::Result<()> {
+    let path = std::path::Path::new("output/terrain.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/terrain.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let grass = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.35, 0.5, 0.25,
+    )))));
+    let hills = Heightfield::from_perlin(
+        &Perlin::new(),
+        0.15,
+        6,
+        512,
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 6.0, 20.0),
+        grass,
+    );
+    world.add(Arc::new(hills));
+
+    // The earth map doubles as its own heightmap, draped with its colours.
+    let earth_texture = Arc::new(ImageTexture::new("earthmap.png"));
+    let relief = Heightfield::from_image(
+        "earthmap.png",
+        Point3::new(-6.0, 1.0, 2.0),
+        Vec3::new(12.0, 0.8, 6.0),
+        Arc::new(Lambertian::new(earth_texture)),
+    );
+    world.add(Arc::new(relief));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 50.0;
+    cam.lookfrom = Point3::new(0.0, 9.0, 16.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
     cam.vup = Vec3::new(0.0, 1.0, 0.0);
 
+    cam.background = Color::new(0.7, 0.8, 1.0);
     cam.defocus_angle = 0.0;
+
     cam.initialize();
+
     cam.render(&world, &mut out)?;
     Ok(())
 }
 
+pub fn hair() -> io::Result<()> {
+    let path = std::path::Path::new("output/hair.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/hair.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.3, 0.25, 0.2,
+    )))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        ground,
+    )));
+
+    // A fur ball: a sphere covered in tapering hair strands.
+    let skin = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.4, 0.25, 0.15,
+    )))));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 1.6, 0.0),
+        1.0,
+        Some(skin),
+    )));
+    let fur = curve::load_hair(
+        "objects/furball.hair",
+        1.0,
+        0.03,
+        0.005,
+        CurveType::Cylinder,
+        Arc::new(KajiyaKay::new(Color::new(0.8, 0.55, 0.3), 0.4, 40.0)),
+    );
+    world.add(Arc::new(Translate::new(
+        Arc::new(fur),
+        Vec3::new(0.0, 1.6, 0.0),
+    )));
+
+    // A tuft of grass blades as flat ribbons.
+    let blade = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.3, 0.6, 0.15,
+    )))));
+    let mut grass = Vec::new();
+    for _ in 0..60 {
+        let root = Point3::new(
+            2.5 + random_double() * 1.2,
+            0.0,
+            random_double() * 1.2 - 0.6,
+        );
+        let lean = Vec3::new(random_double() - 0.5, 0.0, random_double() - 0.5);
+        let height = 0.8 + random_double() * 0.6;
+        let cp = [
+            root,
+            root + Vec3::new(0.0, height * 0.4, 0.0),
+            root + Vec3::new(0.0, height * 0.8, 0.0) + lean * 0.3,
+            root + Vec3::new(0.0, height, 0.0) + lean,
+        ];
+        grass.extend(Curve::new_segments(
+            cp,
+            0.06,
+            0.0,
+            CurveType::Flat,
+            blade.clone(),
+            4,
+        ));
+    }
+    world.add(Arc::new(LinearBVH::new(&grass, &BVHOptions::sah(4, 16))));
+
+    // A thick tube, to show the tapering width on a cylinder.
+    let tube = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.8), 0.2));
+    world.add(Arc::new(Curve::new(
+        [
+            Point3::new(-3.5, 0.2, 0.5),
+            Point3::new(-3.5, 3.0, 1.0),
+            Point3::new(-1.5, 0.5, -1.0),
+            Point3::new(-2.0, 2.5, -0.5),
+        ],
+        0.4,
+        0.1,
+        CurveType::Cylinder,
+        tube,
+    )));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 35.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.2, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn mesh_formats() -> io::Result<()> {
+    let path = std::path::Path::new("output/mesh_formats.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/mesh_formats.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        ground,
+    )));
+
+    let clay: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+        Color::new(0.7, 0.6, 0.5),
+    ))));
+    let steel: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.6, 0.6, 0.65), 0.3));
+
+    // Back row: the two PLY encodings and the two STL encodings.
+    let models = [
+        ply::load_ply_model("objects/pyramid_ascii.ply", 1.0, clay.clone()),
+        ply::load_ply_model("objects/pyramid_binary.ply", 1.0, clay),
+        stl::load_stl_model("objects/prism_ascii.stl", 1.5, steel.clone()),
+        stl::load_stl_model("objects/prism_binary.stl", 1.5, steel),
+    ];
+    for (k, model) in models.into_iter().enumerate() {
+        world.add(Arc::new(Translate::new(
+            Arc::new(model),
+            Vec3::new(-4.5 + 3.0 * k as f64, 0.0, -3.0),
+        )));
+    }
+
+    // Front: a glTF scene with a node hierarchy and three PBR materials.
+    world.add(Arc::new(gltf_model::load_gltf_model("objects/cubes.gltf", 1.0)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 9.0);
+    cam.lookat = Point3::new(0.0, 0.7, -1.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn motion_blur() -> io::Result<()> {
+    let path = std::path::Path::new("output/motion_blur.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/motion_blur.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        ground,
+    )));
+
+    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.7, 0.1, 0.1,
+    )))));
+    let blue = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.1, 0.2, 0.7,
+    )))));
+    let unit_box: Arc<dyn Hittable> = quad::make_box(
+        Point3::new(-0.5, -0.5, -0.5),
+        Point3::new(0.5, 0.5, 0.5),
+        red,
+    );
+
+    // A box sliding to the right.
+    world.add(Arc::new(MotionInstance::new(
+        unit_box.clone(),
+        vec![
+            Keyframe::translation(0.0, Vec3::new(-3.5, 0.5, 0.0)),
+            Keyframe::translation(1.0, Vec3::new(-2.5, 0.5, 0.0)),
+        ],
+    )));
+
+    // A tilted box spinning a quarter turn about its own axis.
+    let tilt = Quaternion::from_axis_angle(Vec3::new(1.0, 0.0, 1.0), 30.0);
+    let spin = |degrees: f64| tilt * Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), degrees);
+    world.add(Arc::new(MotionInstance::new(
+        unit_box.clone(),
+        [0.0, 45.0, 90.0]
+            .iter()
+            .enumerate()
+            .map(|(k, degrees)| {
+                Keyframe::new(
+                    k as f64 / 2.0,
+                    Vec3::new(0.0, 1.0, 0.0),
+                    spin(*degrees),
+                    Vec3::new(1.2, 1.2, 1.2),
+                )
+            })
+            .collect(),
+    )));
+
+    // A sphere growing and rising, keyed only over the first half of the
+    // shutter.
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere::static_new(
+        Point3::new(0.0, 0.0, 0.0),
+        0.5,
+        Some(blue),
+    ));
+    world.add(Arc::new(MotionInstance::new(
+        sphere,
+        vec![
+            Keyframe::new(
+                0.0,
+                Vec3::new(3.0, 0.5, 0.0),
+                Quaternion::identity(),
+                Vec3::new(1.0, 1.0, 1.0),
+            ),
+            Keyframe::new(
+                0.5,
+                Vec3::new(3.0, 1.2, 0.0),
+                Quaternion::identity(),
+                Vec3::new(1.4, 1.4, 1.4),
+            ),
+        ],
+    )));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, 9.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+    cam.shutter_open = 0.0;
+    cam.shutter_close = 1.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn camera_motion() -> io::Result<()> {
+    let path = std::path::Path::new("output/camera_motion.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/camera_motion.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-50.0, 0.0, -50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0),
+        ground,
+    )));
+
+    for k in 0..7 {
+        let x = -6.0 + 2.0 * k as f64;
+        let albedo = Color::new(0.2 + 0.1 * k as f64, 0.3, 0.9 - 0.1 * k as f64);
+        let mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))));
+        world.add(Arc::new(Sphere::static_new(
+            Point3::new(x, 0.7, 0.0),
+            0.7,
+            Some(mat),
+        )));
+    }
+    let world = BVHNode::new_from_list(&mut world);
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(-2.0, 3.0, 10.0);
+    cam.lookat = Point3::new(-1.0, 0.7, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    // A pan to the right while zooming in, with the blur fading in and out
+    // as a triangular shutter would give.
+    cam.lookat_close = Some(Point3::new(1.0, 0.7, 0.0));
+    cam.vfov_close = Some(30.0);
+    cam.shutter_open = 0.0;
+    cam.shutter_close = 1.0;
+    cam.shutter_curve = ShutterCurve::Triangle;
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn projections() -> io::Result<()> {
+    std::fs::create_dir_all("output/projections").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-50.0, 0.0, -50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0),
+        ground,
+    )));
+
+    // A ring of spheres around the origin, so every direction has something
+    // to look at.
+    for k in 0..8 {
+        let angle = k as f64 * PI / 4.0;
+        let albedo = Color::new(
+            0.5 + 0.4 * angle.cos(),
+            0.5 + 0.4 * angle.sin(),
+            0.3 + 0.05 * k as f64,
+        );
+        world.add(Arc::new(Sphere::static_new(
+            Point3::new(4.0 * angle.cos(), 0.8, 4.0 * angle.sin()),
+            0.8,
+            Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))),
+        )));
+    }
+    world.add(quad::make_box(
+        Point3::new(-0.5, 0.0, -0.5),
+        Point3::new(0.5, 2.5, 0.5),
+        Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.05)),
+    ));
+    let world = BVHNode::new_from_list(&mut world);
+
+    let views = [
+        (
+            "orthographic",
+            Projection::Orthographic { height: 11.0 },
+            16.0 / 9.0,
+            Point3::new(6.0, 8.0, 6.0),
+        ),
+        (
+            "fisheye",
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equisolid,
+                fov: 180.0,
+            },
+            1.0,
+            Point3::new(0.0, 3.5, 4.0),
+        ),
+        (
+            "fisheye_wide",
+            Projection::Fisheye {
+                mapping: FisheyeMapping::Equidistant,
+                fov: 240.0,
+            },
+            1.0,
+            Point3::new(0.0, 3.5, 4.0),
+        ),
+        (
+            "equirectangular",
+            Projection::Equirectangular,
+            2.0,
+            Point3::new(1.5, 1.2, 1.5),
+        ),
+        (
+            "cubemap",
+            Projection::CubeMap,
+            1.5,
+            Point3::new(1.5, 1.2, 1.5),
+        ),
+    ];
+
+    for (name, projection, aspect_ratio, lookfrom) in views {
+        let file = File::create(format!("output/projections/{}.ppm", name))
+            .expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(aspect_ratio, 400);
+
+        cam.aspect_ratio = aspect_ratio;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 64;
+        cam.max_depth = 50;
+
+        cam.projection = projection;
+        cam.lookfrom = lookfrom;
+        cam.lookat = Point3::new(0.0, 0.8, 0.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+        cam.background = Color::new(0.7, 0.8, 1.0);
+        cam.defocus_angle = 0.0;
+
+        cam.initialize();
+
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn stereo() -> io::Result<()> {
+    std::fs::create_dir_all("output/stereo").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        1.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-50.0, 0.0, -50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0),
+        ground,
+    )));
+
+    // Spheres at increasing depths, to make the parallax easy to see.
+    for k in 0..12 {
+        let angle = k as f64 * PI / 6.0;
+        let distance = 1.5 + 0.5 * k as f64;
+        let albedo = Color::new(
+            0.5 + 0.4 * angle.cos(),
+            0.5 + 0.4 * angle.sin(),
+            0.2 + 0.05 * k as f64,
+        );
+        world.add(Arc::new(Sphere::static_new(
+            Point3::new(distance * angle.sin(), 0.4, -distance * angle.cos()),
+            0.4,
+            Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))),
+        )));
+    }
+    let world = BVHNode::new_from_list(&mut world);
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 64;
+    cam.max_depth = 50;
+
+    cam.vfov = 60.0;
+    cam.lookfrom = Point3::new(0.0, 1.0, 1.0);
+    cam.lookat = Point3::new(0.0, 0.4, -3.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    // Scene units are metres; zero parallax at the third sphere out.
+    cam.interpupillary_distance = 0.064;
+    cam.convergence_dist = 3.0;
+
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+
+    let file = File::create("output/stereo/pair.ppm").expect("Failed to create file");
+    cam.render_stereo(&world, &mut BufWriter::new(file), StereoLayout::SideBySide)?;
+
+    let left = File::create("output/stereo/left.ppm").expect("Failed to create file");
+    let right = File::create("output/stereo/right.ppm").expect("Failed to create file");
+    cam.render_stereo_pair(
+        &world,
+        &mut BufWriter::new(left),
+        &mut BufWriter::new(right),
+    )?;
+
+    // An omni-directional stereo panorama, left eye on top.
+    cam.projection = Projection::Equirectangular;
+    cam.aspect_ratio = 2.0;
+    cam.lookfrom = Point3::new(0.0, 1.2, 0.0);
+    cam.initialize();
+
+    let file = File::create("output/stereo/ods.ppm").expect("Failed to create file");
+    cam.render_stereo(&world, &mut BufWriter::new(file), StereoLayout::TopBottom)?;
+    Ok(())
+}
+
+pub fn bokeh() -> io::Result<()> {
+    std::fs::create_dir_all("output/bokeh").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.3, 0.3, 0.3,
+    )))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-50.0, 0.0, -50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0),
+        ground,
+    )));
+
+    // The subject, close to the camera.
+    let subject = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.05));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 0.6, 0.0),
+        0.6,
+        Some(subject),
+    )));
+
+    // Small bright lights far behind it turn into bokeh highlights.
+    for k in 0..40 {
+        let x = -12.0 + 24.0 * random_double();
+        let y = 0.5 + 6.0 * random_double();
+        let z = -15.0 - 10.0 * random_double();
+        let tint = Color::new(
+            0.5 + 0.5 * random_double(),
+            0.5 + 0.5 * random_double(),
+            0.5 + 0.5 * random_double(),
+        );
+        let light = Arc::new(DiffuseLight::new_from_color(tint * (8.0 + k as f64 % 3.0)));
+        world.add(Arc::new(Sphere::static_new(
+            Point3::new(x, y, z),
+            0.15,
+            Some(light),
+        )));
+    }
+    let world = BVHNode::new_from_list(&mut world);
+
+    let apertures = [
+        (
+            "hexagon",
+            ApertureShape::Polygon {
+                blades: 6,
+                rotation: 15.0,
+            },
+        ),
+        (
+            "star",
+            ApertureShape::Image(Arc::new(ApertureMask::new("aperture_star.png"))),
+        ),
+    ];
+
+    for (name, aperture) in apertures {
+        let file = File::create(format!("output/bokeh/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 256;
+        cam.max_depth = 20;
+
+        cam.vfov = 30.0;
+        cam.lookfrom = Point3::new(0.0, 1.2, 5.0);
+        cam.lookat = Point3::new(0.0, 1.5, -10.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+        cam.background = Color::new(0.02, 0.02, 0.05);
+        cam.defocus_angle = 3.0;
+        cam.aperture = aperture;
+        cam.vignetting = 0.6;
+        cam.chromatic_aberration = 0.01;
+
+        // Focus on the sphere, in the lower middle of the frame.
+        let (i, j) = (
+            cam.image_width / 2,
+            (cam.image_width as f64 / cam.aspect_ratio * 0.76) as usize,
+        );
+        cam.autofocus(&world, i, j);
+
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn exposure() -> io::Result<()> {
+    std::fs::create_dir_all("output/exposure").expect("Cannot create all the parents");
+
+    // Scene units are metres and the sky is a bright overcast, in cd/m^2.
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.25,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.6, 0.6, 0.6),
+    ));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -40.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 45.0),
+        Arc::new(Lambertian::new(checker)),
+    )));
+
+    // A row of balls receding from 2.5 m to 14 m.
+    let count = 8;
+    for k in 0..count {
+        let t = k as f64 / (count - 1) as f64;
+        let distance = 2.5 + 11.5 * t;
+        let albedo = Color::new(0.8 - 0.6 * t, 0.3, 0.2 + 0.6 * t);
+        world.add(Arc::new(Sphere::static_new(
+            Point3::new(distance * (0.12 - 0.24 * t), 0.12, -distance),
+            0.12,
+            Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))))),
+        )));
+    }
+
+    // A ball rolling across at 5 m/s, at the focus distance.
+    let rolling = Arc::new(Sphere::static_new(
+        Point3::new(0.0, 0.0, 0.0),
+        0.12,
+        Some(Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.1))),
+    ));
+    world.add(Arc::new(MotionInstance::new(
+        rolling,
+        vec![
+            Keyframe::translation(0.0, Vec3::new(-0.2, 0.12, -4.0)),
+            Keyframe::translation(1.0, Vec3::new(4.8, 0.12, -4.0)),
+        ],
+    )));
+    let world = BVHNode::new_from_list(&mut world);
+
+    // Equal exposure values, so equal brightness, traded between depth of
+    // field, motion blur and (on real film) grain.
+    let settings = [
+        ("wide_open", Exposure::new(1.4, 1.0 / 2000.0, 100.0)),
+        ("stopped_down", Exposure::new(5.6, 1.0 / 125.0, 100.0)),
+        ("high_iso", Exposure::new(5.6, 1.0 / 2000.0, 1600.0)),
+    ];
+
+    for (name, exposure) in settings {
+        let file = File::create(format!("output/exposure/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 100;
+        cam.max_depth = 20;
+
+        cam.vfov = 10.0;
+        cam.lookfrom = Point3::new(0.0, 0.6, 0.0);
+        cam.lookat = Point3::new(0.0, 0.12, -6.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.focus_dist = 4.0;
+
+        cam.background = Color::new(4000.0, 4000.0, 4200.0);
+        cam.exposure = Some(exposure);
+        cam.exposure_compensation = -0.3;
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn tilt_shift() -> io::Result<()> {
+    std::fs::create_dir_all("output/tilt_shift").expect("Cannot create all the parents");
+
+    // A grid of city blocks, in metres.
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.35, 0.35, 0.35,
+    )))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-500.0, 0.0, -500.0),
+        Vec3::new(1000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1000.0),
+        ground,
+    )));
+
+    for bx in -5..=5 {
+        for bz in -5..=5 {
+            let x = bx as f64 * 10.0;
+            let z = bz as f64 * 10.0;
+            let height = 6.0 + 24.0 * random_double();
+            let shade = 0.4 + 0.5 * random_double();
+            let wall = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+                shade,
+                shade * 0.9,
+                shade * 0.8,
+            )))));
+            world.add(quad::make_box(
+                Point3::new(x - 3.0, 0.0, z - 3.0),
+                Point3::new(x + 3.0, height, z + 3.0),
+                wall,
+            ));
+        }
+    }
+    let world = BVHNode::new_from_list(&mut world);
+
+    let views = ["tilted_up", "shifted", "miniature"];
+    for name in views {
+        let file = File::create(format!("output/tilt_shift/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(1.0, 400);
+
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 64;
+        cam.max_depth = 10;
+
+        cam.vfov = 50.0;
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.background = Color::new(0.7, 0.8, 1.0);
+
+        match name {
+            // Pointing the camera up makes the walls lean in.
+            "tilted_up" => {
+                cam.lookfrom = Point3::new(0.0, 1.7, 70.0);
+                cam.lookat = Point3::new(0.0, 14.0, 45.0);
+            }
+            // Looking level and shifting the lens up keeps them upright.
+            "shifted" => {
+                cam.lookfrom = Point3::new(0.0, 1.7, 70.0);
+                cam.lookat = Point3::new(0.0, 1.7, 45.0);
+                cam.shift_y = 0.45;
+            }
+            // From high up, a reverse tilt turns the plane of focus nearly
+            // edge-on, leaving a thin sharp band that makes the city look
+            // like a model.
+            _ => {
+                cam.lookfrom = Point3::new(0.0, 90.0, 140.0);
+                cam.lookat = Point3::new(0.0, 0.0, 0.0);
+                cam.vfov = 35.0;
+                cam.focus_dist = (cam.lookfrom - cam.lookat).length();
+                // About f/2.8 for this focal length.
+                cam.defocus_angle = 0.01;
+                cam.tilt = -8.0;
+            }
+        }
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn filters() -> io::Result<()> {
+    std::fs::create_dir_all("output/filters").expect("Cannot create all the parents");
+
+    // Fine detail and hard edges: a checkerboard running to the horizon,
+    // thin posts against the sky and a bright bar that shows up ringing.
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.5,
+        Color::new(0.05, 0.05, 0.05),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-200.0, 0.0, -200.0),
+        Vec3::new(400.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 400.0),
+        Arc::new(Lambertian::new(checker)),
+    )));
+
+    let post = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.05, 0.05, 0.05,
+    )))));
+    for k in 0..5 {
+        let x = -2.0 + k as f64;
+        world.add(quad::make_box(
+            Point3::new(x, 0.0, -4.0 - k as f64),
+            Point3::new(x + 0.04, 3.0, -3.96 - k as f64),
+            post.clone(),
+        ));
+    }
+
+    let bar = Arc::new(DiffuseLight::new_from_color(Color::new(8.0, 8.0, 8.0)));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-1.5, 1.6, -3.0),
+        Vec3::new(3.0, 0.4, 0.0),
+        Vec3::new(0.0, 0.05, 0.0),
+        bar,
+    )));
+    let world = BVHNode::new_from_list(&mut world);
+
+    let filters = [
+        ("box", Filter::Box { radius: 0.5 }),
+        ("tent", Filter::Tent { radius: 1.0 }),
+        (
+            "gaussian",
+            Filter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            },
+        ),
+        (
+            "mitchell",
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+        ),
+        (
+            "lanczos",
+            Filter::Lanczos {
+                radius: 3.0,
+                tau: 3.0,
+            },
+        ),
+        ("blackman_harris", Filter::BlackmanHarris { radius: 1.5 }),
+    ];
+
+    for (name, filter) in filters {
+        let file = File::create(format!("output/filters/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 200);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 200;
+        cam.samples_per_pixel = 100;
+        cam.max_depth = 10;
+        cam.filter = filter;
+
+        cam.vfov = 40.0;
+        cam.lookfrom = Point3::new(0.0, 1.5, 2.0);
+        cam.lookat = Point3::new(0.0, 1.2, -5.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.background = Color::new(0.7, 0.8, 1.0);
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn crop_render() -> io::Result<()> {
+    std::fs::create_dir_all("output/crop").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.5, 0.5, 0.5,
+    )))));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Some(ground),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+            Color::new(0.7, 0.2, 0.1),
+        ))))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Dielectric::new(1.5))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 0.2))),
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-1.5, 5.0, -1.5),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 3.0),
+        Arc::new(DiffuseLight::new_from_color(Color::new(6.0, 6.0, 6.0))),
+    )));
+    let world = BVHNode::new_from_list(&mut world);
+
+    let camera = || {
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.max_depth = 20;
+
+        cam.vfov = 40.0;
+        cam.lookfrom = Point3::new(0.0, 2.5, 9.0);
+        cam.lookat = Point3::new(0.0, 1.0, 0.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.background = Color::new(0.05, 0.05, 0.08);
+        cam
+    };
+
+    // A quick, noisy pass over the whole frame.
+    {
+        let file = File::create("output/crop/full.ppm").expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+        let mut cam = camera();
+        cam.samples_per_pixel = 16;
+        cam.tile_order = TileOrder::Spiral;
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+        out.flush()?;
+    }
+
+    // The glass sphere again with many more samples, pasted over it.
+    {
+        let file = File::create("output/crop/touched_up.ppm").expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+        let mut cam = camera();
+        cam.samples_per_pixel = 400;
+        cam.crop = Some(CropRegion::Normalized {
+            x0: 0.36,
+            y0: 0.3,
+            x1: 0.64,
+            y1: 0.85,
+        });
+        cam.composite_onto = Some("output/crop/full.ppm".to_string());
+        cam.tile_size = 8;
+        cam.tile_order = TileOrder::Hilbert;
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+
+    // Just the metal sphere, on its own.
+    let file = File::create("output/crop/region.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+    let mut cam = camera();
+    cam.samples_per_pixel = 100;
+    cam.crop = Some(CropRegion::Pixels {
+        x0: 260,
+        y0: 60,
+        x1: 360,
+        y1: 190,
+    });
+    cam.initialize();
+    cam.render(&world, &mut out)
+}
+
+pub fn environment_light() -> io::Result<()> {
+    std::fs::create_dir_all("output/environment").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.6, 0.6, 0.6,
+    )))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        ground,
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+            Color::new(0.7, 0.15, 0.1),
+        ))))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Metal::new(Color::new(0.9, 0.7, 0.3), 0.15))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Dielectric::new(1.5))),
+    )));
+    let world = BVHNode::new_from_list(&mut world);
+
+    for visible in [true, false] {
+        let mut env = EnvironmentLight::new("sky_sun.hdr");
+        env.rotation = 30.0;
+        env.intensity = 0.6;
+        env.visible = visible;
+
+        let name = if visible { "visible" } else { "hidden" };
+        let file = File::create(format!("output/environment/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 100;
+        cam.max_depth = 20;
+
+        cam.vfov = 35.0;
+        cam.lookfrom = Point3::new(0.0, 2.5, 10.0);
+        cam.lookat = Point3::new(0.0, 1.0, 0.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        // A plain studio backdrop where the map is hidden.
+        cam.background = Color::new(0.8, 0.8, 0.8);
+        cam.environment = Some(Arc::new(env));
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn physical_sky() -> io::Result<()> {
+    std::fs::create_dir_all("output/sky").expect("Cannot create all the parents");
+
+    let mut world = HittableList::new();
+
+    let grass = Color::new(0.2, 0.3, 0.1);
+    world.add(Arc::new(Quad::new(
+        Point3::new(-500.0, 0.0, -500.0),
+        Vec3::new(1000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1000.0),
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(grass)))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+            Color::new(0.8, 0.8, 0.8),
+        ))))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0))),
+    )));
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Dielectric::new(1.5))),
+    )));
+    let world = BVHNode::new_from_list(&mut world);
+
+    // Midsummer at 40 degrees north, and a hazy day, each with an exposure
+    // a photographer might pick.
+    let skies = [
+        (
+            "noon",
+            PhysicalSky::from_date(2.5, grass, 6, 21, 12.0, 40.0),
+            Exposure::new(16.0, 1.0 / 125.0, 100.0),
+        ),
+        (
+            "afternoon",
+            PhysicalSky::from_date(3.0, grass, 6, 21, 17.0, 40.0),
+            Exposure::new(11.0, 1.0 / 125.0, 100.0),
+        ),
+        (
+            "sunset",
+            PhysicalSky::from_date(4.0, grass, 6, 21, 19.2, 40.0),
+            Exposure::new(8.0, 1.0 / 125.0, 100.0),
+        ),
+        (
+            "hazy",
+            PhysicalSky::new(8.0, grass, 25.0, 150.0),
+            Exposure::new(8.0, 1.0 / 125.0, 100.0),
+        ),
+    ];
+
+    for (name, mut sky, exposure) in skies {
+        eprintln!(
+            "{}: sun {:.1} degrees up",
+            name,
+            sky.sun_direction().y().asin().to_degrees()
+        );
+        // The sky is in kcd/m^2 and the exposure expects cd/m^2.
+        sky.intensity = 1000.0;
+
+        let file = File::create(format!("output/sky/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 100;
+        cam.max_depth = 20;
+
+        cam.vfov = 60.0;
+        cam.lookfrom = Point3::new(0.0, 1.5, 7.0);
+        cam.lookat = Point3::new(0.0, 2.0, 0.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.environment = Some(Arc::new(sky));
+        cam.exposure = Some(exposure);
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn delta_lights() -> io::Result<()> {
+    std::fs::create_dir_all("output/lights").expect("Cannot create all the parents");
+
+    let white = || -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+            0.73, 0.73, 0.73,
+        )))))
+    };
+
+    let mut stage = HittableList::new();
+    stage.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        white(),
+    )));
+    stage.add(Arc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -4.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 20.0, 0.0),
+        white(),
+    )));
+    stage.add(Arc::new(Sphere::static_new(
+        Point3::new(-2.5, 1.0, 0.0),
+        1.0,
+        Some(Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+            Color::new(0.8, 0.3, 0.2),
+        ))))),
+    )));
+    stage.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 1.2, -0.5),
+        1.2,
+        Some(white()),
+    )));
+    stage.add(quad::make_box(
+        Point3::new(1.8, 0.0, -1.0),
+        Point3::new(3.4, 1.6, 0.6),
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+            0.2, 0.4, 0.7,
+        ))))),
+    ));
+
+    let lights: Vec<Arc<dyn Light>> = vec![
+        // A warm bulb off to the left.
+        Arc::new(PointLight::new(
+            Point3::new(-4.0, 3.5, 2.0),
+            Color::new(12.0, 9.0, 6.0),
+        )),
+        // A spot from above picking out the middle sphere.
+        Arc::new(SpotLight::new(
+            Point3::new(0.0, 6.0, 2.0),
+            Point3::new(0.0, 1.0, -0.5),
+            Color::new(40.0, 40.0, 40.0),
+            18.0,
+            12.0,
+        )),
+        // Faint blue moonlight from the right.
+        Arc::new(DirectionalLight::new(
+            Vec3::new(-1.0, -1.5, -0.5),
+            Color::new(0.05, 0.07, 0.15),
+        )),
+    ];
+
+    // The same stage in the clear, and filled with a thin haze that shows
+    // the beams.
+    for (name, haze) in [("clear", 0.0), ("haze", 0.04)] {
+        let mut world = stage.clone();
+        if haze > 0.0 {
+            world.add(Arc::new(ConstantMedium::new_with_color(
+                quad::make_box(
+                    Point3::new(-10.0, 0.01, -3.99),
+                    Point3::new(10.0, 10.0, 8.0),
+                    white(),
+                ),
+                haze,
+                Color::new(1.0, 1.0, 1.0),
+            )));
+        }
+        let world = BVHNode::new_from_list(&mut world);
+
+        let file =
+            File::create(format!("output/lights/{}.ppm", name)).expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+
+        let mut cam = Camera::new(16.0 / 9.0, 400);
+
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 400;
+        cam.samples_per_pixel = 100;
+        cam.max_depth = 20;
+        cam.background = Color::new(0.0, 0.0, 0.0);
+        cam.lights = lights.clone();
+
+        cam.vfov = 40.0;
+        cam.lookfrom = Point3::new(0.0, 3.0, 10.0);
+        cam.lookat = Point3::new(0.0, 1.2, 0.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+        cam.initialize();
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn simple_light() -> io::Result<()> {
+    let path = std::path::Path::new("output/book2/image18ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/book2/image18.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+    let mut world = HittableList::new();
+
+    let pertext = Arc::new(NoiseTexture::new(4.0));
+
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Some(Arc::new(Lambertian::new(pertext.clone()))),
+    )));
+
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Some(Arc::new(Lambertian::new(pertext.clone()))),
+    )));
+
+    let difflight = Arc::new(DiffuseLight::new_from_color(Color::new(4.0, 4.0, 4.0)));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(3.0, 1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        difflight.clone(),
+    )));
+
+    world.add(Arc::new(Sphere::static_new(
+        Point3::new(0.0, 7.0, 0.0),
+        2.0,
+        Some(difflight),
+    )));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 10;
+    cam.max_depth = 50;
+    cam.background = Color::new(0.0, 0.0, 0.0);
+
+    cam.vfov = 20.0;
+    cam.lookfrom = Point3::new(26.0, 3.0, 6.0);
+    cam.lookat = Point3::new(0.0, 2.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    cam.initialize();
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn cornell_box() -> io::Result<()> {
+    let path = std::path::Path::new("output/final.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/final.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let red = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.65, 0.05, 0.05,
+    )))));
+    let white = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.73, 0.73, 0.73,
+    )))));
+    let green = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+        0.12, 0.45, 0.15,
+    )))));
+    let light = Arc::new(DiffuseLight::new_from_color(Color::new(15.0, 15.0, 15.0)));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green.clone(),
+    )));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red.clone(),
+    )));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light.clone(),
+    )));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white.clone(),
+    )));
+
+    let box1 = quad::make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    );
+    let box1 = Arc::new(RotateY::new(box1, 15.0));
+    let box1 = Arc::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0)));
+    world.add(box1);
+
+    /*let box2 = quad::make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white.clone(),
+    );
+    let box2 = Arc::new(RotateY::new(box2, -18.0));
+    let box2 = Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0)));
+    world.add(box2);*/
+    let coffin = Arc::new(obj::load_obj_model("objects/coffin.obj", 100.0));
+    let coffin = Arc::new(Translate::new(coffin, Vec3::new(100.0, 0.0, 200.0)));
+    world.add(coffin);
+
+    let mut cam = Camera::new(1.0, 600);
+
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 1000;
+    cam.max_depth = 50;
+    cam.background = Color::new(0.0, 0.0, 0.0);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(278.0, 278.0, -800.0);
+    cam.lookat = Point3::new(278.0, 278.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+    cam.initialize();
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn coffin_instances() -> io::Result<()> {
+    let path = std::path::Path::new("output/instances.ppm");
+    let prefix = path.parent().unwrap();
+    std::fs::create_dir_all(prefix).expect("Cannot create all the parents");
+
+    let file = File::create("output/instances.ppm").expect("Failed to create file");
+    let mut out = BufWriter::new(file);
+
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        50.0,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-1000.0, 0.0, -1000.0),
+        Vec3::new(2000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2000.0),
+        ground,
+    )));
+
+    // The mesh and its BVH are built once and shared by every instance.
+    let coffin: Arc<dyn Hittable> = Arc::new(obj::load_obj_model("objects/coffin.obj", 100.0));
+    let gold: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1));
+
+    let mut instances = HittableList::new();
+    for a in 0..10 {
+        for b in 0..10 {
+            // Each copy gets its own size, a slight lean and a heading.
+            let transform = Transform::uniform_scale(0.8 + 0.4 * random_double())
+                .then(&Transform::rotate_x(random_double() * 6.0 - 3.0))
+                .then(&Transform::rotate_z(random_double() * 6.0 - 3.0))
+                .then(&Transform::rotate_y(random_double() * 360.0))
+                .then(&Transform::translate(Vec3::new(
+                    a as f64 * 150.0,
+                    0.0,
+                    b as f64 * 150.0,
+                )));
+            if (a + b) % 7 == 0 {
+                instances.add(Arc::new(Instance::new_with_material(
+                    coffin.clone(),
+                    transform,
+                    gold.clone(),
+                )));
+            } else {
+                instances.add(Arc::new(Instance::new(coffin.clone(), transform)));
+            }
+        }
+    }
+    world.add(Arc::new(BVHNode::new_from_list(&mut instances)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(-600.0, 700.0, -600.0);
+    cam.lookat = Point3::new(700.0, 0.0, 700.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+    cam.initialize();
+    cam.render(&world, &mut out)?;
+    Ok(())
+}
+
+pub fn coffin_animation() -> io::Result<()> {
+    std::fs::create_dir_all("output/animation").expect("Cannot create all the parents");
+
+    let ground: Arc<dyn Hittable> = Arc::new(Quad::new(
+        Point3::new(-1000.0, 0.0, -1000.0),
+        Vec3::new(2000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2000.0),
+        Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::new(
+            0.48, 0.83, 0.53,
+        ))))),
+    ));
+    let coffin: Arc<dyn Hittable> = Arc::new(obj::load_obj_model("objects/coffin.obj", 100.0));
+
+    let frame_count = 24;
+    let place = |frame: usize| -> Vec<Arc<dyn Hittable>> {
+        let phase = frame as f64 / frame_count as f64 * 2.0 * PI;
+        let mut objects = vec![ground.clone()];
+        for k in 0..30 {
+            let angle = phase + k as f64 * 2.0 * PI / 30.0;
+            let radius = 300.0 + 100.0 * (3.0 * angle).sin();
+            let transform = Transform::rotate_y(angle.to_degrees()).then(&Transform::translate(
+                Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin()),
+            ));
+            objects.push(Arc::new(Instance::new(coffin.clone(), transform)));
+        }
+        objects
+    };
+
+    // Built once; each frame only refits unless the tree degrades.
+    let mut world = LinearBVH::new(&place(0), &BVHOptions::sah(2, 16));
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 25;
+    cam.max_depth = 20;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 800.0, -900.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+    cam.initialize();
+
+    for frame in 0..frame_count {
+        if frame > 0 {
+            world.set_primitives(&place(frame));
+            if world.update() {
+                eprintln!("Frame {}: BVH rebuilt", frame);
+            }
+        }
+
+        let file = File::create(format!("output/animation/frame_{:03}.ppm", frame))
+            .expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+        cam.render(&world, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn deforming_mesh() -> io::Result<()> {
+    std::fs::create_dir_all("output/deform").expect("Cannot create all the parents");
+
+    let checker = Arc::new(Lambertian::new(Arc::new(CheckerTexture::from_colors(
+        0.2,
+        Color::new(0.8, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    let center = Point3::new(0.0, 0.0, 0.0);
+    let triangles: Vec<Arc<Triangle>> = tessellated_sphere(center, 1.0, 48, 96, checker)
+        .into_iter()
+        .map(Arc::new)
+        .collect();
+    let rest: Vec<[Point3; 3]> = triangles.iter().map(|t| t.vertices()).collect();
+
+    // Built once; each frame moves the vertices in place and refits.
+    let mut blob = LinearBVH::new(&triangles, &BVHOptions::sah(4, 16));
+    // Let the BVH hold the only references, so the edits need no copies.
+    drop(triangles);
+
+    let mut cam = Camera::new(1.0, 300);
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 300;
+    cam.samples_per_pixel = 25;
+    cam.max_depth = 20;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.vfov = 30.0;
+    cam.lookfrom = Point3::new(0.0, 2.0, 6.0);
+    cam.lookat = center;
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.initialize();
+
+    let frame_count = 12;
+    for frame in 0..frame_count {
+        // Ripples running down the sphere, pushing the surface in and out.
+        let phase = frame as f64 / frame_count as f64 * 2.0 * PI;
+        let wobble = |p: Point3| center + (p - center) * (1.0 + 0.12 * (6.0 * p.y - phase).sin());
+        for (index, corners) in rest.iter().enumerate() {
+            let [q, a, b] = corners.map(wobble);
+            blob.primitive_mut(index).set_vertices(q, a, b);
+        }
+        if blob.update() {
+            eprintln!("Frame {}: BVH rebuilt", frame);
+        }
+
+        let file = File::create(format!("output/deform/frame_{:03}.ppm", frame))
+            .expect("Failed to create file");
+        let mut out = BufWriter::new(file);
+        cam.render(&blob, &mut out)?;
+    }
+    Ok(())
+}
+
+pub fn bvh_comparison() -> io::Result<()> {
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+        Color::new(0.73, 0.73, 0.73),
+    ))));
+
+    // The coffin's small triangles next to the Cornell box's huge walls,
+    // the uneven case a median split handles badly.
+    let mut world = obj::load_obj_triangles("objects/coffin.obj", 100.0);
+    for (q, u, v) in [
+        (
+            Point3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+        ),
+        (
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+        ),
+        (
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+        ),
+        (
+            Point3::new(555.0, 555.0, 555.0),
+            Vec3::new(-555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -555.0),
+        ),
+        (
+            Point3::new(0.0, 0.0, 555.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+        ),
+    ] {
+        world.add(Arc::new(Quad::new(q, u, v, white.clone())));
+    }
+
+    for (name, options) in [
+        ("median", BVHOptions::median()),
+        ("SAH", BVHOptions::sah(4, 16)),
+    ] {
+        let bvh = BVHNode::new_with_options(&world.objects, &options);
+        eprintln!("{}: {}", name, bvh.stats());
+    }
+    Ok(())
+}
+
+// A UV sphere of triangles, `rings` bands from pole to pole with
+// `segments` quads around each.
+fn tessellated_sphere(
+    center: Point3,
+    radius: f64,
+    rings: usize,
+    segments: usize,
+    mat: Arc<dyn Material>,
+) -> Vec<Triangle> {
+    let point = |ring: usize, segment: usize| {
+        let theta = PI * ring as f64 / rings as f64;
+        let phi = 2.0 * PI * segment as f64 / segments as f64;
+        center
+            + Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            ) * radius
+    };
+    let mut triangles = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b) = (point(ring, segment), point(ring, segment + 1));
+            let (c, d) = (point(ring + 1, segment), point(ring + 1, segment + 1));
+            if ring > 0 {
+                triangles.push(Triangle::new_with_points(a, c, b, mat.clone()));
+            }
+            if ring < rings - 1 {
+                triangles.push(Triangle::new_with_points(b, c, d, mat.clone()));
+            }
+        }
+    }
+    triangles
+}
+
+pub fn bvh_benchmark() -> io::Result<()> {
+    let mut world = HittableList::new();
+    let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+        Color::new(0.5, 0.5, 0.5),
+    ))));
+    for triangle in tessellated_sphere(Point3::new(0.0, -1000.0, 0.0), 1000.0, 64, 128, ground) {
+        world.add(Arc::new(triangle));
+    }
+    for a in 0..4 {
+        for b in 0..4 {
+            let albedo = Color::random() * Color::random();
+            let mat: Arc<dyn Material> =
+                Arc::new(Lambertian::new(Arc::new(SolidColor::new(albedo))));
+            let center = Point3::new(a as f64 * 2.5 - 3.75, 1.0, b as f64 * 2.5 - 3.75);
+            for triangle in tessellated_sphere(center, 1.0, 64, 128, mat) {
+                world.add(Arc::new(triangle));
+            }
+        }
+    }
+    eprintln!("{} triangles", world.objects.len());
+
+    let mut cam = Camera::new(16.0 / 9.0, 400);
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 16;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 6.0, 12.0);
+    cam.lookat = Point3::new(0.0, 0.5, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.initialize();
+
+    // The same frame through the old median-split pointer tree, the SAH
+    // pointer tree and the flattened SAH tree.
+    let time = |name: &str, build: &dyn Fn() -> Arc<dyn Hittable>| -> io::Result<()> {
+        let start = std::time::Instant::now();
+        let bvh = build();
+        let built = start.elapsed();
+
+        let start = std::time::Instant::now();
+        cam.render(bvh.as_ref(), &mut io::sink())?;
+        eprintln!(
+            "{}: build {:.2?}, render {:.2?}",
+            name,
+            built,
+            start.elapsed()
+        );
+        Ok(())
+    };
+    let (median, sah) = (BVHOptions::median(), BVHOptions::sah(4, 16));
+    time("BVHNode, median", &|| {
+        Arc::new(BVHNode::new_with_options(&world.objects, &median))
+    })?;
+    time("BVHNode, SAH", &|| {
+        Arc::new(BVHNode::new_with_options(&world.objects, &sah))
+    })?;
+    time("LinearBVH, SAH", &|| {
+        Arc::new(LinearBVH::new(&world.objects, &sah))
+    })?;
+    Ok(())
+}
+
 fn main() -> io::Result<()> {
     cornell_box()
 }
@@ -1,5 +1,5 @@
 use crate::onb::ONB;
-use crate::pdf::{CosinePdf, Pdf};
+use crate::pdf::{CosinePdf, Pdf, SpherePdf};
 use crate::rtweekend::random_double;
 use crate::texture::{CheckerTexture, SolidColor, Texture};
 use crate::vec3::{Point3, Vec3};
@@ -91,7 +91,7 @@ impl Material for Metal {
         srec.attenuation = self.albedo;
         srec.pdf_ptr = None;
         srec.skip_pdf = true;
-        srec.skip_pdf_ray = Ray::new_with_time(rec.p, reflected, r_in.time());
+        srec.skip_pdf_ray = rec.spawn_ray(reflected, r_in.time());
         true
     }
 }
@@ -138,7 +138,7 @@ impl Material for Dielectric {
                 Vec3::refract(unit_direction, rec.normal, ri)
             };
 
-        srec.skip_pdf_ray = Ray::new_with_time(rec.p, direction, r_in.time());
+        srec.skip_pdf_ray = rec.spawn_ray(direction, r_in.time());
         true
     }
 }
@@ -196,3 +196,80 @@ impl Material for Isotropic {
         0.25 / PI
     }
 }
+
+// Lookup resolution for the specular normalisation, over |cos| between the
+// outgoing direction and the fibre.
+const KAJIYA_KAY_TABLE_SIZE: usize = 32;
+
+// Kajiya-Kay hair shading. Light scatters around the fibre tangent rather than
+// a surface normal: a diffuse lobe proportional to the sine against the
+// tangent, plus a specular cone mirrored about the normal plane of the fibre.
+// Both lobes are normalised over the sphere so `albedo` is the total
+// reflectance, and `specular` splits it between them.
+pub struct KajiyaKay {
+    albedo: Color,
+    specular: f64,
+    exponent: f64,
+    spec_norm: [f64; KAJIYA_KAY_TABLE_SIZE],
+}
+
+impl KajiyaKay {
+    pub fn new(albedo: Color, specular: f64, exponent: f64) -> Self {
+        let specular = specular.clamp(0.0, 1.0);
+        let exponent = exponent.max(0.0);
+
+        // The lobe only depends on the cosines against the tangent, so its
+        // integral over the sphere reduces to one over cos(theta_i).
+        let steps = 1024;
+        let mut spec_norm = [0.0; KAJIYA_KAY_TABLE_SIZE];
+        for (bin, norm) in spec_norm.iter_mut().enumerate() {
+            let cos_o = (bin as f64 + 0.5) / KAJIYA_KAY_TABLE_SIZE as f64;
+            let mut integral = 0.0;
+            for k in 0..steps {
+                let cos_i = -1.0 + 2.0 * (k as f64 + 0.5) / steps as f64;
+                integral += Self::cone(cos_i, cos_o, exponent) * 2.0 / steps as f64;
+            }
+            *norm = 1.0 / (2.0 * PI * integral).max(1e-12);
+        }
+
+        Self {
+            albedo,
+            specular,
+            exponent,
+            spec_norm,
+        }
+    }
+
+    // cos(theta_i + theta_o) raised to the exponent, with the angles
+    // measured from the tangent.
+    fn cone(cos_i: f64, cos_o: f64, exponent: f64) -> f64 {
+        let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+        (sin_i * sin_o - cos_i * cos_o).max(0.0).powf(exponent)
+    }
+}
+
+impl Material for KajiyaKay {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, srec: &mut scatter_record) -> bool {
+        srec.attenuation = self.albedo;
+        srec.pdf_ptr = Some(Arc::new(SpherePdf));
+        srec.skip_pdf = false;
+        true
+    }
+
+    fn scattering_pdf(&self, r: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if rec.tangent.length_squared() == 0.0 {
+            return 0.25 / PI;
+        }
+        let t = Vec3::unit_vector(rec.tangent);
+        let cos_o = Vec3::dot(-Vec3::unit_vector(r.direction()), t).clamp(-1.0, 1.0);
+        let cos_i = Vec3::dot(Vec3::unit_vector(scattered.direction()), t).clamp(-1.0, 1.0);
+
+        let diffuse = (1.0 - cos_i * cos_i).max(0.0).sqrt() / (PI * PI);
+        let bin =
+            ((cos_o.abs() * KAJIYA_KAY_TABLE_SIZE as f64) as usize).min(KAJIYA_KAY_TABLE_SIZE - 1);
+        let specular = Self::cone(cos_i, cos_o, self.exponent) * self.spec_norm[bin];
+
+        (1.0 - self.specular) * diffuse + self.specular * specular
+    }
+}
@@ -0,0 +1,159 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::quaternion::Quaternion;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// Samples per keyframe interval when bounding the swept object.
+const BOUND_STEPS: usize = 64;
+
+// The pose of an object at one moment: scaled, then rotated, then moved.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn translation(time: f64, translation: Vec3) -> Self {
+        Self::new(
+            time,
+            translation,
+            Quaternion::identity(),
+            Vec3::new(1.0, 1.0, 1.0),
+        )
+    }
+}
+
+// An object whose placement changes over the shutter. Rays are transformed
+// by the pose at their own time, found by interpolating the two keyframes
+// around it: translation and scale linearly, rotation by slerp. Before the
+// first key and after the last the object holds still.
+pub struct MotionInstance {
+    object: Arc<dyn Hittable>,
+    keys: Vec<Keyframe>,
+    bbox: AABB,
+}
+
+impl MotionInstance {
+    pub fn new(object: Arc<dyn Hittable>, mut keys: Vec<Keyframe>) -> Self {
+        assert!(!keys.is_empty(), "a motion needs at least one keyframe");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let mut instance = Self {
+            object,
+            keys,
+            bbox: AABB::empty(),
+        };
+        instance.bbox = instance.motion_bounds();
+        instance
+    }
+
+    pub fn transform_at(&self, time: f64) -> Transform {
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+        if time <= keys[0].time || last == 0 {
+            return Self::pose(&keys[0]);
+        }
+        if time >= keys[last].time {
+            return Self::pose(&keys[last]);
+        }
+
+        let i = keys.partition_point(|k| k.time <= time) - 1;
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let t = (time - a.time) / (b.time - a.time);
+        Transform::from_trs(
+            a.translation * (1.0 - t) + b.translation * t,
+            a.rotation.slerp(&b.rotation, t),
+            a.scale * (1.0 - t) + b.scale * t,
+        )
+    }
+
+    fn pose(key: &Keyframe) -> Transform {
+        Transform::from_trs(key.translation, key.rotation, key.scale)
+    }
+
+    // Union of the object's box at closely spaced times. Between samples a
+    // point can swing off the chord by at most its distance from the pivot
+    // times the angle turned, so the union is padded by that much.
+    fn motion_bounds(&self) -> AABB {
+        let local = self.object.bounding_box();
+        let mut bbox = Self::pose(&self.keys[0]).bounding_box(&local);
+
+        let mut reach: f64 = 0.0;
+        for x in [local.x.min, local.x.max] {
+            for y in [local.y.min, local.y.max] {
+                for z in [local.z.min, local.z.max] {
+                    reach = reach.max(Vec3::new(x, y, z).length());
+                }
+            }
+        }
+
+        for pair in self.keys.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let max_scale = [a.scale, b.scale]
+                .iter()
+                .flat_map(|s| [s.x.abs(), s.y.abs(), s.z.abs()])
+                .fold(0.0, f64::max);
+            let step_angle = a.rotation.angle_to(&b.rotation) / BOUND_STEPS as f64;
+            let pad = reach * max_scale * step_angle;
+
+            for k in 1..=BOUND_STEPS {
+                let time = a.time + (b.time - a.time) * k as f64 / BOUND_STEPS as f64;
+                let pose = if k == BOUND_STEPS {
+                    Self::pose(b)
+                } else {
+                    self.transform_at(time)
+                };
+                let mut step = pose.bounding_box(&local);
+                if pad > 0.0 {
+                    step = AABB::from_points(
+                        Point3::new(step.x.min - pad, step.y.min - pad, step.z.min - pad),
+                        Point3::new(step.x.max + pad, step.y.max + pad, step.z.max + pad),
+                    );
+                }
+                bbox = AABB::from_boxes(bbox, step);
+            }
+        }
+        bbox
+    }
+}
+
+impl Hittable for MotionInstance {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let transform = self.transform_at(r.time());
+        let local_r = Ray::new_with_time(
+            transform.inverse_point(r.origin()),
+            transform.inverse_vector(r.direction()),
+            r.time(),
+        );
+
+        if !self.object.hit(&local_r, ray_t, rec) {
+            return false;
+        }
+
+        rec.p = transform.point(rec.p);
+        rec.normal = Vec3::unit_vector(transform.normal(rec.normal));
+        rec.tangent = transform.vector(rec.tangent);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
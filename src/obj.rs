@@ -1,6 +1,7 @@
-use crate::bvh::BVHNode;
+use crate::bvh::BVHOptions;
 use crate::color::Color;
 use crate::hittable_list::HittableList;
+use crate::linear_bvh::LinearBVH;
 use crate::material::{Lambertian, Material, Metal};
 use crate::texture::{ImageTexture, SolidColor};
 use crate::triangle::Triangle;
@@ -9,6 +10,14 @@ use std::sync::Arc;
 use tobj;
 
 pub fn load_obj_model(path: &str, scale: f64) -> HittableList {
+    let object = load_obj_triangles(path, scale);
+    let bvh = LinearBVH::new(&object.objects, &BVHOptions::sah(4, 16));
+    eprintln!("Loaded {}: {}", path, bvh.stats());
+    HittableList::from(Arc::new(bvh))
+}
+
+// The triangles of an OBJ file as a flat list, without a BVH over them.
+pub fn load_obj_triangles(path: &str, scale: f64) -> HittableList {
     let mut object = HittableList::new();
 
     let (models, materials) = tobj::load_obj(
@@ -81,6 +90,5 @@ pub fn load_obj_model(path: &str, scale: f64) -> HittableList {
             object.add(Arc::new(triangle));
         }
     }
-    let object = HittableList::from(Arc::new(BVHNode::new_from_list(&mut object)));
     object
 }
@@ -0,0 +1,371 @@
+use crate::bvh::BVHOptions;
+use crate::color::Color;
+use crate::hittable_list::HittableList;
+use crate::linear_bvh::LinearBVH;
+use crate::material::{Lambertian, Material};
+use crate::texture::SolidColor;
+use crate::triangle::Triangle;
+use crate::vec3::Point3;
+use std::fs;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PlyType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(PlyType::Int8),
+            "uchar" | "uint8" => Some(PlyType::UInt8),
+            "short" | "int16" => Some(PlyType::Int16),
+            "ushort" | "uint16" => Some(PlyType::UInt16),
+            "int" | "int32" => Some(PlyType::Int32),
+            "uint" | "uint32" => Some(PlyType::UInt32),
+            "float" | "float32" => Some(PlyType::Float32),
+            "double" | "float64" => Some(PlyType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::UInt8 => 1,
+            PlyType::Int16 | PlyType::UInt16 => 2,
+            PlyType::Int32 | PlyType::UInt32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+}
+
+struct PlyProperty {
+    name: String,
+    kind: PlyType,
+    // Type of the length prefix for list properties.
+    count_kind: Option<PlyType>,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+// Reads scalars from the body of a PLY file in any of its three encodings.
+struct PlyReader<'a> {
+    format: PlyFormat,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl PlyReader<'_> {
+    fn read(&mut self, kind: PlyType) -> f64 {
+        if self.format == PlyFormat::Ascii {
+            while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let token = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or("");
+            return token
+                .parse()
+                .unwrap_or_else(|_| panic!("PLY: bad value '{}'", token));
+        }
+
+        let size = kind.size();
+        let Some(bytes) = self.data.get(self.pos..self.pos + size) else {
+            panic!("PLY: unexpected end of data");
+        };
+        self.pos += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == PlyFormat::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        match kind {
+            PlyType::Int8 => buf[0] as i8 as f64,
+            PlyType::UInt8 => buf[0] as f64,
+            PlyType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            PlyType::UInt16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            PlyType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            PlyType::UInt32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            PlyType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            PlyType::Float64 => f64::from_le_bytes(buf),
+        }
+    }
+}
+
+fn parse_header(text: &str, path: &str) -> (PlyFormat, Vec<PlyElement>) {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        panic!("{}: not a PLY file", path);
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", name, _version] => {
+                format = match *name {
+                    "ascii" => Some(PlyFormat::Ascii),
+                    "binary_little_endian" => Some(PlyFormat::BinaryLittleEndian),
+                    "binary_big_endian" => Some(PlyFormat::BinaryBigEndian),
+                    _ => panic!("{}: unknown PLY format '{}'", path, name),
+                }
+            }
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{}: bad element count '{}'", path, count)),
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_kind, kind, name] => {
+                let element = elements
+                    .last_mut()
+                    .unwrap_or_else(|| panic!("{}: property before any element", path));
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PlyType::parse(kind)
+                        .unwrap_or_else(|| panic!("{}: unknown type '{}'", path, kind)),
+                    count_kind: Some(
+                        PlyType::parse(count_kind)
+                            .unwrap_or_else(|| panic!("{}: unknown type '{}'", path, count_kind)),
+                    ),
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements
+                    .last_mut()
+                    .unwrap_or_else(|| panic!("{}: property before any element", path));
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PlyType::parse(kind)
+                        .unwrap_or_else(|| panic!("{}: unknown type '{}'", path, kind)),
+                    count_kind: None,
+                });
+            }
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+
+    let format = format.unwrap_or_else(|| panic!("{}: PLY header has no format line", path));
+    (format, elements)
+}
+
+// Loads a triangle mesh from a PLY file, ASCII or binary. Polygons are split
+// into fans. Files with per-vertex `red`/`green`/`blue` get one Lambertian
+// per face with the average colour of its corners; otherwise every face
+// uses `mat`.
+pub fn load_ply_model(path: &str, scale: f64, mat: Arc<dyn Material>) -> HittableList {
+    let data = fs::read(path).expect("Failed to load PLY file");
+    let mut object = HittableList::new();
+    for triangle in read_ply(&data, path, scale, mat) {
+        object.add(Arc::new(triangle));
+    }
+
+    let bvh = LinearBVH::new(&object.objects, &BVHOptions::sah(4, 16));
+    eprintln!("Loaded {}: {}", path, bvh.stats());
+    HittableList::from(Arc::new(bvh))
+}
+
+// The triangles in the contents of a PLY file; `path` is only for messages.
+fn read_ply(data: &[u8], path: &str, scale: f64, mat: Arc<dyn Material>) -> Vec<Triangle> {
+    let header_end = data
+        .windows(10)
+        .position(|w| w == b"end_header")
+        .unwrap_or_else(|| panic!("{}: PLY header has no end_header", path));
+    let body_start = data[header_end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |i| header_end + i + 1);
+    let header = String::from_utf8_lossy(&data[..header_end + 10]);
+    let (format, elements) = parse_header(&header, path);
+
+    let mut reader = PlyReader {
+        format,
+        data,
+        pos: body_start,
+    };
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut faces: Vec<Vec<usize>> = Vec::new();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut p = [0.0; 3];
+            let mut c = [0.0; 3];
+            let mut has_color = false;
+            for property in &element.properties {
+                if let Some(count_kind) = property.count_kind {
+                    let count = reader.read(count_kind) as usize;
+                    let values: Vec<usize> = (0..count)
+                        .map(|_| reader.read(property.kind) as usize)
+                        .collect();
+                    if element.name == "face"
+                        && (property.name == "vertex_indices" || property.name == "vertex_index")
+                    {
+                        faces.push(values);
+                    }
+                    continue;
+                }
+
+                let value = reader.read(property.kind);
+                if element.name != "vertex" {
+                    continue;
+                }
+                match property.name.as_str() {
+                    "x" => p[0] = value,
+                    "y" => p[1] = value,
+                    "z" => p[2] = value,
+                    "red" | "green" | "blue" => {
+                        let k = ["red", "green", "blue"]
+                            .iter()
+                            .position(|n| *n == property.name)
+                            .unwrap();
+                        // Integer channels are 0..255, float ones already 0..1.
+                        c[k] = match property.kind {
+                            PlyType::Float32 | PlyType::Float64 => value,
+                            _ => value / 255.0,
+                        };
+                        has_color = true;
+                    }
+                    _ => {}
+                }
+            }
+            if element.name == "vertex" {
+                positions.push(Point3::new(p[0], p[1], p[2]) * scale);
+                if has_color {
+                    colors.push(Color::new(c[0], c[1], c[2]));
+                }
+            }
+        }
+    }
+
+    let use_colors = !colors.is_empty() && colors.len() == positions.len();
+    let mut triangles = Vec::new();
+    for face in &faces {
+        if face.len() < 3 || face.iter().any(|&i| i >= positions.len()) {
+            eprintln!("{}: skipping face with bad indices {:?}", path, face);
+            continue;
+        }
+        let face_mat: Arc<dyn Material> = if use_colors {
+            let sum = face
+                .iter()
+                .fold(Color::new(0.0, 0.0, 0.0), |acc, &i| acc + colors[i]);
+            Arc::new(Lambertian::new(Arc::new(SolidColor::new(
+                sum / face.len() as f64,
+            ))))
+        } else {
+            mat.clone()
+        };
+        for k in 1..face.len() - 1 {
+            triangles.push(Triangle::new_with_points(
+                positions[face[0]],
+                positions[face[k]],
+                positions[face[k + 1]],
+                face_mat.clone(),
+            ));
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_fixture(name: &str) -> Vec<Triangle> {
+        let path = format!("objects/{}", name);
+        let data = fs::read(&path).expect("Failed to load PLY fixture");
+        let grey = Color::new(0.5, 0.5, 0.5);
+        read_ply(
+            &data,
+            &path,
+            1.0,
+            Arc::new(Lambertian::new(Arc::new(SolidColor::new(grey)))),
+        )
+    }
+
+    fn assert_close(a: Color, b: Color) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn ascii_pyramid() {
+        let triangles = read_fixture("pyramid_ascii.ply");
+        // The square base becomes a fan of two, plus four sides.
+        assert_eq!(triangles.len(), 6);
+
+        let bbox = Triangle::bounds(&triangles);
+        assert_eq!((bbox.x.min, bbox.x.max), (-1.0, 1.0));
+        assert_eq!((bbox.y.min, bbox.y.max), (0.0, 1.5));
+        assert_eq!((bbox.z.min, bbox.z.max), (-1.0, 1.0));
+
+        let grey = Arc::new(Lambertian::new(Arc::new(SolidColor::new(Color::zero()))));
+        let corner = |x, z| Point3::new(x, 0.0, z);
+        let fan = [
+            Triangle::new_with_points(
+                corner(-1.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                grey.clone(),
+            ),
+            Triangle::new_with_points(
+                corner(-1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(-1.0, 1.0),
+                grey,
+            ),
+        ];
+        assert!(triangles[0].same_vertices(&fan[0]));
+        assert!(triangles[1].same_vertices(&fan[1]));
+
+        // Each face takes the mean of its corner colours.
+        let base = Color::new(120.0, 120.0, 80.0) / 255.0;
+        assert_close(triangles[0].albedo(), base);
+        assert_close(triangles[1].albedo(), base);
+        let side = Color::new(480.0, 480.0, 320.0) / (3.0 * 255.0);
+        assert_close(triangles[2].albedo(), side);
+    }
+
+    #[test]
+    fn binary_matches_ascii() {
+        let ascii = read_fixture("pyramid_ascii.ply");
+        let binary = read_fixture("pyramid_binary.ply");
+        assert_eq!(binary.len(), ascii.len());
+        assert!(ascii.iter().zip(&binary).all(|(a, b)| a.same_vertices(b)));
+
+        // Without vertex colours every face uses the given material.
+        for triangle in &binary {
+            assert_close(triangle.albedo(), Color::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn big_endian_matches_little_endian() {
+        let little = read_fixture("pyramid_binary.ply");
+        let big = read_fixture("pyramid_binary_be.ply");
+        assert_eq!(big.len(), little.len());
+        assert!(little.iter().zip(&big).all(|(a, b)| a.same_vertices(b)));
+    }
+}
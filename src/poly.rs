@@ -0,0 +1,171 @@
+// Closed-form polynomial root finders after Schwarze, "Cubic and Quartic
+// Roots" (Graphics Gems I). Coefficients are given highest degree first and
+// roots are returned unsorted as a fixed array plus a count.
+
+const EPSILON: f64 = 1e-12;
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPSILON
+}
+
+// Roots of a x^2 + b x + c, falling back to the linear case when a is zero.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> ([f64; 2], usize) {
+    if a == 0.0 {
+        if b == 0.0 {
+            return ([0.0; 2], 0);
+        }
+        return ([-c / b, 0.0], 1);
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return ([0.0; 2], 0);
+    }
+
+    // Avoids the cancellation of -b + sqrt(d) when b is large.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return ([0.0, 0.0], 2);
+    }
+    ([q / a, c / q], 2)
+}
+
+// Roots of x^3 + a x^2 + b x + c.
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> ([f64; 3], usize) {
+    // Substitute x = y - a/3 to get y^3 + 3p y + 2q = 0.
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let mut roots = [0.0; 3];
+    let count = if is_zero(d) {
+        if is_zero(q) {
+            1
+        } else {
+            let u = (-q).cbrt();
+            roots[0] = 2.0 * u;
+            roots[1] = -u;
+            2
+        }
+    } else if d < 0.0 {
+        // Three real roots.
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        roots[0] = t * phi.cos();
+        roots[1] = -t * (phi + std::f64::consts::PI / 3.0).cos();
+        roots[2] = -t * (phi - std::f64::consts::PI / 3.0).cos();
+        3
+    } else {
+        let sqrt_d = d.sqrt();
+        roots[0] = (sqrt_d - q).cbrt() - (sqrt_d + q).cbrt();
+        1
+    };
+
+    let sub = a / 3.0;
+    for root in roots.iter_mut().take(count) {
+        *root -= sub;
+    }
+    (roots, count)
+}
+
+// Roots of a x^4 + b x^3 + c x^2 + d x + e. The closed form loses a few
+// digits, so every root is polished with Newton steps on the original
+// polynomial.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> ([f64; 4], usize) {
+    let mut roots = [0.0; 4];
+    if a == 0.0 {
+        let (cubic, count) = solve_cubic_general(b, c, d, e);
+        roots[..count].copy_from_slice(&cubic[..count]);
+        return (roots, count);
+    }
+
+    let (a3, a2, a1, a0) = (b / a, c / a, d / a, e / a);
+
+    // Substitute x = y - a3/4 to get y^4 + p y^2 + q y + r = 0.
+    let sq_a = a3 * a3;
+    let p = -3.0 / 8.0 * sq_a + a2;
+    let q = sq_a * a3 / 8.0 - a3 * a2 / 2.0 + a1;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * a2 / 16.0 - a3 * a1 / 4.0 + a0;
+
+    let mut count = 0;
+    if is_zero(r) {
+        // y (y^3 + p y + q) = 0
+        let (cubic, n) = solve_cubic(0.0, p, q);
+        roots[..n].copy_from_slice(&cubic[..n]);
+        roots[n] = 0.0;
+        count = n + 1;
+    } else {
+        // Take one real root of the resolvent cubic to split into two quadratics.
+        let (cubic, _) = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0);
+        let z = cubic[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return (roots, 0);
+        };
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return (roots, 0);
+        };
+
+        let v = if q < 0.0 { -v } else { v };
+        for (lin, con) in [(v, z - u), (-v, z + u)] {
+            let (quad, n) = solve_quadratic(1.0, lin, con);
+            roots[count..count + n].copy_from_slice(&quad[..n]);
+            count += n;
+        }
+    }
+
+    let sub = a3 / 4.0;
+    for root in roots.iter_mut().take(count) {
+        *root -= sub;
+        *root = polish_root(*root, [a, b, c, d, e]);
+    }
+    (roots, count)
+}
+
+fn solve_cubic_general(a: f64, b: f64, c: f64, d: f64) -> ([f64; 3], usize) {
+    if a == 0.0 {
+        let (quad, n) = solve_quadratic(b, c, d);
+        return ([quad[0], quad[1], 0.0], n);
+    }
+    solve_cubic(b / a, c / a, d / a)
+}
+
+fn evaluate(x: f64, coeffs: &[f64; 5]) -> (f64, f64) {
+    let mut f = 0.0;
+    let mut df = 0.0;
+    for &coeff in coeffs {
+        df = df * x + f;
+        f = f * x + coeff;
+    }
+    (f, df)
+}
+
+// Newton iteration that only keeps steps which reduce the residual.
+fn polish_root(mut x: f64, coeffs: [f64; 5]) -> f64 {
+    let (mut f, mut df) = evaluate(x, &coeffs);
+    for _ in 0..4 {
+        if df == 0.0 || f == 0.0 {
+            break;
+        }
+        let next = x - f / df;
+        let (next_f, next_df) = evaluate(next, &coeffs);
+        if next_f.abs() >= f.abs() {
+            break;
+        }
+        (x, f, df) = (next, next_f, next_df);
+    }
+    x
+}
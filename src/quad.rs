@@ -1,4 +1,6 @@
+use crate::onb::ONB;
 use crate::rtweekend::random_double;
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 use crate::{
@@ -11,11 +13,25 @@ use crate::{
     vec3::{Point3, Vec3},
 };
 
+// Outline of a planar primitive in the plane spanned by `u` and `v`.
+//
+// Parallelograms and triangles are anchored at the corner `q`; ellipses and
+// annuli are centred on `q` and use `u` and `v` as their semi-axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanarShape {
+    Parallelogram,
+    Triangle,
+    Ellipse,
+    // Inner radius as a fraction of the outer one.
+    Annulus(f64),
+}
+
 pub struct Quad {
     q: Point3,
     u: Vec3,
     v: Vec3,
     w: Vec3,
+    shape: PlanarShape,
     mat: Arc<dyn Material>,
     bbox: AABB,
     normal: Vec3,
@@ -25,16 +41,63 @@ pub struct Quad {
 
 impl Quad {
     pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Quad::new_with_shape(q, u, v, PlanarShape::Parallelogram, mat)
+    }
+
+    pub fn new_triangle(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Quad::new_with_shape(q, u, v, PlanarShape::Triangle, mat)
+    }
+
+    pub fn new_ellipse(center: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Quad::new_with_shape(center, u, v, PlanarShape::Ellipse, mat)
+    }
+
+    pub fn new_disk(center: Point3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let uvw = ONB::new(normal);
+        Quad::new_ellipse(center, uvw.u() * radius, uvw.v() * radius, mat)
+    }
+
+    pub fn new_annulus(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let uvw = ONB::new(normal);
+        let inner = (inner_radius / outer_radius).clamp(0.0, 1.0);
+        Quad::new_with_shape(
+            center,
+            uvw.u() * outer_radius,
+            uvw.v() * outer_radius,
+            PlanarShape::Annulus(inner),
+            mat,
+        )
+    }
+
+    pub fn new_with_shape(
+        q: Point3,
+        u: Vec3,
+        v: Vec3,
+        shape: PlanarShape,
+        mat: Arc<dyn Material>,
+    ) -> Self {
         let n = Vec3::cross(u, v);
         let normal = Vec3::unit_vector(n);
         let D = Vec3::dot(q, normal);
         let w = n / Vec3::dot(n, n);
-        let area = n.length();
+        let area = match shape {
+            PlanarShape::Parallelogram => n.length(),
+            PlanarShape::Triangle => n.length() / 2.0,
+            PlanarShape::Ellipse => PI * n.length(),
+            PlanarShape::Annulus(inner) => PI * n.length() * (1.0 - inner * inner),
+        };
         let mut quad = Self {
             q,
             u,
             v,
             w,
+            shape,
             mat,
             bbox: AABB::empty(),
             normal,
@@ -46,20 +109,88 @@ impl Quad {
     }
 
     fn set_bounding_box(&mut self) {
-        let diagonal1 = AABB::from_points(self.q, self.q + self.u + self.v);
-        let diagonal2 = AABB::from_points(self.q + self.u, self.q + self.v);
-        self.bbox = AABB::from_boxes(diagonal1, diagonal2);
+        self.bbox = match self.shape {
+            PlanarShape::Parallelogram => {
+                let diagonal1 = AABB::from_points(self.q, self.q + self.u + self.v);
+                let diagonal2 = AABB::from_points(self.q + self.u, self.q + self.v);
+                AABB::from_boxes(diagonal1, diagonal2)
+            }
+            PlanarShape::Triangle => AABB::from_boxes(
+                AABB::from_points(self.q, self.q + self.u),
+                AABB::from_points(self.q + self.v, self.q + self.v),
+            ),
+            PlanarShape::Ellipse | PlanarShape::Annulus(_) => {
+                // Exact extents of an ellipse q + u cos(t) + v sin(t).
+                let extent = Vec3::new(
+                    self.u.x.hypot(self.v.x),
+                    self.u.y.hypot(self.v.y),
+                    self.u.z.hypot(self.v.z),
+                );
+                AABB::from_points(self.q - extent, self.q + extent)
+            }
+        };
+        self.bbox.pad_to_minimums();
     }
 
-    fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
+    // Tests the plane coordinates of a hit against the outline and fills in
+    // the texture coordinates.
+    fn is_interior(&self, a: f64, b: f64, rec: &mut HitRecord) -> bool {
         let unit_interval = Interval::new(0.0, 1.0);
-        if !unit_interval.contains(a) || !unit_interval.contains(b) {
-            return false;
+        match self.shape {
+            PlanarShape::Parallelogram => {
+                if !unit_interval.contains(a) || !unit_interval.contains(b) {
+                    return false;
+                }
+                rec.u = a;
+                rec.v = b;
+            }
+            PlanarShape::Triangle => {
+                if a < 0.0 || b < 0.0 || a + b > 1.0 {
+                    return false;
+                }
+                rec.u = a;
+                rec.v = b;
+            }
+            PlanarShape::Ellipse | PlanarShape::Annulus(_) => {
+                let inner = match self.shape {
+                    PlanarShape::Annulus(inner) => inner,
+                    _ => 0.0,
+                };
+                let radius = a.hypot(b);
+                if radius > 1.0 || radius < inner {
+                    return false;
+                }
+                let phi = b.atan2(a);
+                rec.u = if phi < 0.0 { phi + 2.0 * PI } else { phi } / (2.0 * PI);
+                rec.v = if inner < 1.0 {
+                    (radius - inner) / (1.0 - inner)
+                } else {
+                    0.0
+                };
+            }
         }
-        rec.u = a;
-        rec.v = b;
         true
     }
+
+    // Uniformly distributed point in plane coordinates.
+    fn sample_plane(&self) -> (f64, f64) {
+        match self.shape {
+            PlanarShape::Parallelogram => (random_double(), random_double()),
+            PlanarShape::Triangle => {
+                let (a, b) = (random_double(), random_double());
+                if a + b > 1.0 { (1.0 - a, 1.0 - b) } else { (a, b) }
+            }
+            PlanarShape::Ellipse | PlanarShape::Annulus(_) => {
+                let inner = match self.shape {
+                    PlanarShape::Annulus(inner) => inner,
+                    _ => 0.0,
+                };
+                let radius = (inner * inner + random_double() * (1.0 - inner * inner)).sqrt();
+                let phi = 2.0 * PI * random_double();
+                (radius * phi.cos(), radius * phi.sin())
+            }
+        }
+    }
 }
 
 pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> Arc<HittableList> {
@@ -132,12 +263,13 @@ impl Hittable for Quad {
         let alpha = Vec3::dot(self.w, Vec3::cross(planar_hitpt_vector, self.v));
         let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar_hitpt_vector));
 
-        if !Quad::is_interior(alpha, beta, rec) {
+        if !self.is_interior(alpha, beta, rec) {
             return false;
         }
 
         rec.t = t;
-        rec.p = intersection;
+        // Rebuilt from the plane coordinates, which is more accurate than r.at(t).
+        rec.p = self.q + self.u * alpha + self.v * beta;
         rec.mat = Some(Arc::clone(&self.mat));
         rec.set_face_normal(r, self.normal);
 
@@ -152,7 +284,7 @@ impl Hittable for Quad {
         let ray = Ray::new_with_time(origin, direction, 0.0);
         let mut rec = HitRecord::default();
 
-        if !self.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+        if !self.hit(&ray, Interval::new(0.0, f64::INFINITY), &mut rec) {
             return 0.0;
         }
 
@@ -163,7 +295,8 @@ impl Hittable for Quad {
     }
 
     fn random(&self, origin: Point3) -> Vec3 {
-        let random_point = self.q + self.u * random_double() + self.v * random_double();
+        let (a, b) = self.sample_plane();
+        let random_point = self.q + self.u * a + self.v * b;
 
         random_point - origin
     }
@@ -0,0 +1,435 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::poly::{solve_quadratic, solve_quartic};
+use crate::ray::Ray;
+use crate::rtweekend::{INFINITY, random_double};
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QuadricKind {
+    Cylinder,
+    Cone,
+    Paraboloid,
+    Hyperboloid,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum QuadricPart {
+    Side,
+    Cap(f64),
+}
+
+// Surface of revolution around the y axis through `base`, spanning heights
+// 0 to `height` above it. Every shape here has a squared radius that is a
+// quadratic in y, so they share one intersection routine. Use `Instance` to
+// place them in any other orientation.
+pub struct Quadric {
+    base: Point3,
+    height: f64,
+    // Squared radius at height y is profile[0] + profile[1] y + profile[2] y^2.
+    profile: [f64; 3],
+    capped: bool,
+    kind: QuadricKind,
+    mat: Arc<dyn Material>,
+    bbox: AABB,
+    // Total area for light sampling, zero for shapes that cannot be sampled.
+    area: f64,
+}
+
+impl Quadric {
+    pub fn new_cylinder(
+        base: Point3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let r2 = radius * radius;
+        Self::new(base, height, [r2, 0.0, 0.0], capped, QuadricKind::Cylinder, mat)
+    }
+
+    // Apex at the top, base of `radius` at the bottom.
+    pub fn new_cone(
+        base: Point3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let r2 = radius * radius;
+        let profile = [r2, -2.0 * r2 / height, r2 / (height * height)];
+        Self::new(base, height, profile, capped, QuadricKind::Cone, mat)
+    }
+
+    // Vertex at the base, opening upwards to `radius` at the top.
+    pub fn new_paraboloid(
+        base: Point3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let profile = [0.0, radius * radius / height, 0.0];
+        Self::new(base, height, profile, capped, QuadricKind::Paraboloid, mat)
+    }
+
+    // Hyperboloid of one sheet, narrowest halfway up.
+    pub fn new_hyperboloid(
+        base: Point3,
+        waist_radius: f64,
+        rim_radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let half = height / 2.0;
+        let k = (rim_radius * rim_radius - waist_radius * waist_radius) / (half * half);
+        let profile = [waist_radius * waist_radius + k * half * half, -2.0 * k * half, k];
+        Self::new(base, height, profile, capped, QuadricKind::Hyperboloid, mat)
+    }
+
+    fn new(
+        base: Point3,
+        height: f64,
+        profile: [f64; 3],
+        capped: bool,
+        kind: QuadricKind,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let mut quadric = Self {
+            base,
+            height,
+            profile,
+            capped,
+            kind,
+            mat,
+            bbox: AABB::empty(),
+            area: 0.0,
+        };
+
+        let max_radius = quadric
+            .radius_squared(0.0)
+            .max(quadric.radius_squared(height))
+            .sqrt();
+        let extent = Vec3::new(max_radius, 0.0, max_radius);
+        quadric.bbox = AABB::from_points(
+            base - extent,
+            base + extent + Vec3::new(0.0, height, 0.0),
+        );
+        quadric.bbox.pad_to_minimums();
+        quadric.area = quadric.side_area() + quadric.caps().map(|r2| PI * r2).sum::<f64>();
+        quadric
+    }
+
+    fn radius_squared(&self, y: f64) -> f64 {
+        let [a0, a1, a2] = self.profile;
+        (a0 + a1 * y + a2 * y * y).max(0.0)
+    }
+
+    // Squared radii of the caps that are present.
+    fn caps(&self) -> impl Iterator<Item = f64> + '_ {
+        [0.0, self.height]
+            .into_iter()
+            .map(|y| self.radius_squared(y))
+            .filter(move |&r2| self.capped && r2 > 0.0)
+    }
+
+    fn side_area(&self) -> f64 {
+        let radius = self.radius_squared(0.0).sqrt();
+        match self.kind {
+            QuadricKind::Cylinder => 2.0 * PI * radius * self.height,
+            QuadricKind::Cone => PI * radius * radius.hypot(self.height),
+            // No closed-form uniform sampling, so only their caps, if any,
+            // are light sources.
+            QuadricKind::Paraboloid | QuadricKind::Hyperboloid => 0.0,
+        }
+    }
+
+    // Outward normal of the side at `p`, relative to the base. The gradient
+    // vanishes at the apex of a cone, where the axis stands in for it.
+    fn side_normal(&self, p: Point3) -> Vec3 {
+        let [_, a1, a2] = self.profile;
+        let gradient = Vec3::new(p.x, -(a1 + 2.0 * a2 * p.y) / 2.0, p.z);
+        if gradient.length_squared() == 0.0 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        Vec3::unit_vector(gradient)
+    }
+
+    // The nearest crossing within `ray_t` and the part it lies on.
+    fn intersect(&self, r: &Ray, ray_t: Interval) -> Option<(f64, QuadricPart)> {
+        let o = r.origin() - self.base;
+        let d = r.direction();
+        let [a0, a1, a2] = self.profile;
+
+        let a = d.x * d.x + d.z * d.z - a2 * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z) - a1 * d.y - 2.0 * a2 * o.y * d.y;
+        let c = o.x * o.x + o.z * o.z - a0 - a1 * o.y - a2 * o.y * o.y;
+
+        let mut closest = ray_t.max;
+        let mut part = None;
+
+        let (roots, count) = solve_quadratic(a, b, c);
+        for &t in &roots[..count] {
+            let y = o.y + t * d.y;
+            if ray_t.surrounds(t) && t < closest && (0.0..=self.height).contains(&y) {
+                closest = t;
+                part = Some(QuadricPart::Side);
+            }
+        }
+
+        if self.capped && d.y != 0.0 {
+            for y in [0.0, self.height] {
+                let t = (y - o.y) / d.y;
+                if !ray_t.surrounds(t) || t >= closest {
+                    continue;
+                }
+                let x = o.x + t * d.x;
+                let z = o.z + t * d.z;
+                if x * x + z * z <= self.radius_squared(y) {
+                    closest = t;
+                    part = Some(QuadricPart::Cap(y));
+                }
+            }
+        }
+
+        part.map(|part| (closest, part))
+    }
+
+    fn get_u(p: Point3) -> f64 {
+        let phi = (-p.z).atan2(p.x) + PI;
+        phi / (2.0 * PI)
+    }
+
+    // Uniform point on the surface, relative to the base.
+    fn sample_surface(&self) -> Point3 {
+        let phi = 2.0 * PI * random_double();
+        let mut pick = random_double() * self.area - self.side_area();
+        if pick < 0.0 {
+            let radius = self.radius_squared(0.0).sqrt();
+            let (y, r) = match self.kind {
+                QuadricKind::Cone => {
+                    // Area grows linearly away from the apex.
+                    let f = random_double().sqrt();
+                    (self.height * (1.0 - f), radius * f)
+                }
+                _ => (self.height * random_double(), radius),
+            };
+            return Point3::new(r * phi.cos(), y, r * phi.sin());
+        }
+
+        for y in [0.0, self.height] {
+            let r2 = self.radius_squared(y);
+            if !self.capped || r2 <= 0.0 {
+                continue;
+            }
+            pick -= PI * r2;
+            if pick < 0.0 {
+                let r = (r2 * random_double()).sqrt();
+                return Point3::new(r * phi.cos(), y, r * phi.sin());
+            }
+        }
+        Point3::new(0.0, self.height, 0.0)
+    }
+}
+
+impl Hittable for Quadric {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((closest, part)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+
+        let p = r.origin() - self.base + r.direction() * closest;
+        let outward_normal = match part {
+            QuadricPart::Side => {
+                // Push the point back onto the surface radially.
+                let r_xz = p.x.hypot(p.z);
+                let scale = if r_xz > 0.0 {
+                    self.radius_squared(p.y).sqrt() / r_xz
+                } else {
+                    1.0
+                };
+                let p = Point3::new(p.x * scale, p.y, p.z * scale);
+                rec.p = p;
+                rec.u = Self::get_u(p);
+                rec.v = p.y / self.height;
+                self.side_normal(p)
+            }
+            QuadricPart::Cap(y) => {
+                let p = Point3::new(p.x, y, p.z);
+                rec.p = p;
+                rec.u = Self::get_u(p);
+                rec.v = p.x.hypot(p.z) / self.radius_squared(y).sqrt();
+                Vec3::new(0.0, if y > 0.0 { 1.0 } else { -1.0 }, 0.0)
+            }
+        };
+
+        rec.t = closest;
+        rec.p += self.base;
+        rec.mat = Some(Arc::clone(&self.mat));
+        rec.set_face_normal(r, outward_normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    // A direction can cross the surface several times, and sampling a point
+    // by area may land on any of those crossings, so their densities add up.
+    // Sides that `random` never samples add nothing.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.area <= 0.0 {
+            return 0.0;
+        }
+
+        let ray = Ray::new(origin, direction);
+        let sample_side = self.side_area() > 0.0;
+        let mut t_min = 0.0;
+        let mut pdf = 0.0;
+        while let Some((t, part)) = self.intersect(&ray, Interval::new(t_min, INFINITY)) {
+            t_min = t;
+            let normal = match part {
+                QuadricPart::Side if !sample_side => continue,
+                QuadricPart::Side => self.side_normal(origin - self.base + direction * t),
+                QuadricPart::Cap(_) => Vec3::new(0.0, 1.0, 0.0),
+            };
+            let distance_squared = t * t * direction.length_squared();
+            let cosine = (Vec3::dot(direction, normal) / direction.length()).abs();
+            if cosine > 0.0 {
+                pdf += distance_squared / (cosine * self.area);
+            }
+        }
+        pdf
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        if self.area <= 0.0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        self.base + self.sample_surface() - origin
+    }
+}
+
+// Torus around the y axis through `center`.
+pub struct Torus {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let outer = major_radius + minor_radius;
+        let extent = Vec3::new(outer, minor_radius, outer);
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            mat,
+            bbox: AABB::from_points(center - extent, center + extent),
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let length = r.direction().length();
+        let d = r.direction() / length;
+
+        // Solve from the point of closest approach to the centre with a unit
+        // direction; far-away origins otherwise ruin the quartic's precision.
+        let shift = -Vec3::dot(r.origin() - self.center, d);
+        let o = r.origin() - self.center + d * shift;
+
+        let outer = self.major_radius + self.minor_radius;
+        if o.length_squared() > outer * outer {
+            return false;
+        }
+
+        let r2 = self.major_radius * self.major_radius;
+        let f = Vec3::dot(o, d);
+        let k = o.length_squared() + r2 - self.minor_radius * self.minor_radius;
+        let (roots, count) = solve_quartic(
+            1.0,
+            4.0 * f,
+            4.0 * f * f + 2.0 * k - 4.0 * r2 * (d.x * d.x + d.z * d.z),
+            4.0 * f * k - 8.0 * r2 * (o.x * d.x + o.z * d.z),
+            k * k - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+        );
+
+        let mut closest = ray_t.max;
+        let mut found = false;
+        for &s in &roots[..count] {
+            let t = (s + shift) / length;
+            if ray_t.surrounds(t) && t < closest {
+                closest = t;
+                found = true;
+            }
+        }
+        if !found {
+            return false;
+        }
+
+        let p = r.at(closest) - self.center;
+        let r_xz = p.x.hypot(p.z);
+        let ring = if r_xz > 0.0 {
+            Point3::new(p.x, 0.0, p.z) * (self.major_radius / r_xz)
+        } else {
+            Point3::new(self.major_radius, 0.0, 0.0)
+        };
+        let outward_normal = Vec3::unit_vector(p - ring);
+
+        let theta = p.y.atan2(r_xz - self.major_radius);
+        rec.t = closest;
+        rec.p = self.center + ring + outward_normal * self.minor_radius;
+        rec.u = Quadric::get_u(p);
+        rec.v = (theta + PI) / (2.0 * PI);
+        rec.mat = Some(Arc::clone(&self.mat));
+        rec.set_face_normal(r, outward_normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::EmptyMaterial;
+
+    #[test]
+    fn cone_apex_has_a_normal() {
+        let cone = Quadric::new_cone(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            false,
+            Arc::new(EmptyMaterial),
+        );
+        let origin = Point3::new(0.0, 2.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        let r = Ray::new(origin, down);
+        let mut rec = HitRecord::default();
+        assert!(cone.hit(&r, Interval::new(0.0, INFINITY), &mut rec));
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert_eq!([rec.normal.x, rec.normal.y, rec.normal.z], [0.0, 1.0, 0.0]);
+        assert!(cone.pdf_value(origin, down).is_finite());
+    }
+}
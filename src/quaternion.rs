@@ -0,0 +1,128 @@
+use crate::vec3::Vec3;
+use std::ops::{Add, Mul, Neg};
+
+// Unit quaternion for rotations, w + xi + yj + zk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle_degrees: f64) -> Self {
+        let a = Vec3::unit_vector(axis);
+        let (sin_half, cos_half) = (angle_degrees.to_radians() / 2.0).sin_cos();
+        Self::new(cos_half, a.x * sin_half, a.y * sin_half, a.z * sin_half)
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalized(&self) -> Self {
+        let length = self.dot(self).sqrt();
+        Self::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    // Angle of the rotation from `self` to `other`, in radians.
+    pub fn angle_to(&self, other: &Quaternion) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    // Constant-speed interpolation along the shorter arc.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut end = *other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            end = -end;
+        }
+
+        // Nearly parallel: the arc is a line as far as f64 can tell.
+        if cos_theta > 0.9995 {
+            return (*self * (1.0 - t) + end * t).normalized();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        (*self * a + end * b).normalized()
+    }
+
+    // Rows of the equivalent rotation matrix.
+    pub fn to_rows(self) -> [[f64; 3]; 3] {
+        let Self { w, x, y, z } = self.normalized();
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+    fn add(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w + rhs.w,
+            self.x + rhs.x,
+            self.y + rhs.y,
+            self.z + rhs.z,
+        )
+    }
+}
+
+impl Mul<f64> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, rhs: f64) -> Quaternion {
+        Quaternion::new(self.w * rhs, self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+// Hamilton product: rotating by `rhs` first, then by `self`.
+impl Mul for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+    fn neg(self) -> Quaternion {
+        Quaternion::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
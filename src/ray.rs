@@ -36,3 +36,32 @@ impl Ray {
         self.orig + self.dir * t
     }
 }
+
+// Scale-aware self-intersection avoidance after Wächter and Binder, "A Fast
+// and Robust Method for Avoiding Self-Intersection" (Ray Tracing Gems, ch. 6).
+// The origin is pushed off the surface along the geometric normal by a fixed
+// number of ulps, so the offset grows with the magnitude of the coordinates
+// instead of being a fixed epsilon.
+const OFFSET_ORIGIN: f64 = 1.0 / 32.0;
+const OFFSET_FLOAT_SCALE: f64 = 1.0 / 68_719_476_736.0; // 2^-36
+const OFFSET_INT_SCALE: f64 = 4096.0;
+
+fn offset_component(p: f64, n: f64) -> f64 {
+    if p.abs() < OFFSET_ORIGIN {
+        return p + OFFSET_FLOAT_SCALE * n;
+    }
+    let of_i = (OFFSET_INT_SCALE * n) as i64;
+    let bits = p.to_bits() as i64 + if p < 0.0 { -of_i } else { of_i };
+    f64::from_bits(bits as u64)
+}
+
+// Offsets `p` to the side of the surface with normal `n` that `direction`
+// leaves towards.
+pub fn offset_ray_origin(p: Point3, n: Vec3, direction: Vec3) -> Point3 {
+    let n = if Vec3::dot(direction, n) < 0.0 { -n } else { n };
+    Point3::new(
+        offset_component(p.x, n.x),
+        offset_component(p.y, n.y),
+        offset_component(p.z, n.z),
+    )
+}
@@ -67,7 +67,13 @@ impl RtwImage {
     }
 
     pub fn load(&mut self, filename: &str) -> bool {
-        let res = load_with_depth(filename, self.bytes_per_pixel, true);
+        // HDR files keep their float data for float_pixel_data, while
+        // pixel_data sees stb's own conversion to bytes.
+        let mut res = load_with_depth(filename, self.bytes_per_pixel, false);
+        if let LoadResult::ImageF32(image) = res {
+            self.fdata = Some(image.data);
+            res = load_with_depth(filename, self.bytes_per_pixel, true);
+        }
         match res {
             LoadResult::ImageF32(image) => {
                 self.fdata = Some(image.data);
@@ -111,6 +117,19 @@ impl RtwImage {
         &self.bdata.as_ref().unwrap()[offset..offset + 3]
     }
 
+    // Linear colour of a pixel: the float data of HDR files, or the bytes of
+    // other images with their gamma of 2.2 undone.
+    pub fn float_pixel_data(&self, x: usize, y: usize) -> [f32; 3] {
+        if let Some(fdata) = &self.fdata {
+            let x = Self::clamp(x, 0, self.image_width);
+            let y = Self::clamp(y, 0, self.image_height);
+            let offset = y * self.bytes_per_scanline + x * self.bytes_per_pixel;
+            return [fdata[offset], fdata[offset + 1], fdata[offset + 2]];
+        }
+        let bytes = self.pixel_data(x, y);
+        [0, 1, 2].map(|k| (bytes[k] as f32 / 255.0).powf(2.2))
+    }
+
     fn convert_to_bytes(&mut self) {
         if let Some(fdata) = &self.fdata {
             let total_bytes = self.image_width * self.image_height * self.bytes_per_pixel;
@@ -144,3 +163,32 @@ impl RtwImage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hdr_bytes_come_from_stb() {
+        let image = RtwImage::new("sky_sun.hdr");
+        let LoadResult::ImageU8(bytes) = load_with_depth("images/sky_sun.hdr", 3, true) else {
+            panic!("Failed to load images/sky_sun.hdr as bytes");
+        };
+        let LoadResult::ImageF32(floats) = load_with_depth("images/sky_sun.hdr", 3, false) else {
+            panic!("Failed to load images/sky_sun.hdr as floats");
+        };
+
+        let mut brightest = 0.0f32;
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let offset = 3 * (y * image.width() + x);
+                assert_eq!(image.pixel_data(x, y), &bytes.data[offset..offset + 3]);
+                let pixel = image.float_pixel_data(x, y);
+                assert_eq!(pixel, floats.data[offset..offset + 3]);
+                brightest = brightest.max(pixel[0]);
+            }
+        }
+        // The sun is far brighter than any byte can hold.
+        assert!(brightest > 1.0);
+    }
+}
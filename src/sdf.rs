@@ -0,0 +1,523 @@
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+const DEFAULT_EPSILON: f64 = 1e-4;
+const DEFAULT_MAX_STEPS: usize = 256;
+
+// A signed distance function: negative inside, positive outside, and never
+// larger than the true distance to the surface. `bounding_box` must enclose
+// the whole surface, since marching only happens inside it.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Point3) -> f64;
+    fn bounding_box(&self) -> AABB;
+}
+
+// Sphere-traces an `Sdf`. Steps are the distance bound at the current point,
+// and the surface is reached once that bound drops under `epsilon`.
+pub struct SdfObject {
+    sdf: Arc<dyn Sdf>,
+    mat: Arc<dyn Material>,
+    pub epsilon: f64,
+    pub max_steps: usize,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Arc<dyn Sdf>, mat: Arc<dyn Material>) -> Self {
+        Self {
+            sdf,
+            mat,
+            epsilon: DEFAULT_EPSILON,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::unit_vector(Vec3::new(
+            self.sdf.distance(p + dx) - self.sdf.distance(p - dx),
+            self.sdf.distance(p + dy) - self.sdf.distance(p - dy),
+            self.sdf.distance(p + dz) - self.sdf.distance(p - dz),
+        ))
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Boxes may touch the surface, so march from a little outside them.
+        let bbox = self.sdf.bounding_box();
+        let pad = 4.0 * self.epsilon;
+        let padded = AABB::new(bbox.x.expand(pad), bbox.y.expand(pad), bbox.z.expand(pad));
+        let Some(span) = padded.clip(r, ray_t) else {
+            return false;
+        };
+
+        let length = r.direction().length();
+        let mut t = span.min;
+
+        // Rays refracted into the shape march towards the exit instead.
+        let side = if self.sdf.distance(r.at(t)) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        // A ray that starts on the surface must get clear of it first, or it
+        // would immediately hit the point it left from. Only the ray's own
+        // origin can be such a point.
+        let mut clear = span.min > ray_t.min;
+        let mut found = false;
+
+        for _ in 0..self.max_steps {
+            if t > span.max {
+                return false;
+            }
+            let distance = side * self.sdf.distance(r.at(t));
+            if distance < self.epsilon {
+                if clear {
+                    found = true;
+                    break;
+                }
+                t += self.epsilon / length;
+                continue;
+            }
+            clear = true;
+            t += distance / length;
+        }
+        if !found || t <= ray_t.min {
+            return false;
+        }
+
+        let p = r.at(t);
+        let outward_normal = self.normal(p);
+        if outward_normal.x.is_nan() {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        Sphere::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
+        rec.mat = Some(Arc::clone(&self.mat));
+        rec.set_face_normal(r, outward_normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.sdf.bounding_box()
+    }
+}
+
+// Wraps a closure as an `Sdf`; the caller vouches for the box.
+pub struct FnSdf<F> {
+    f: F,
+    bbox: AABB,
+}
+
+impl<F: Fn(Point3) -> f64 + Send + Sync> FnSdf<F> {
+    pub fn new(bbox: AABB, f: F) -> Self {
+        Self { f, bbox }
+    }
+}
+
+impl<F: Fn(Point3) -> f64 + Send + Sync> Sdf for FnSdf<F> {
+    fn distance(&self, p: Point3) -> f64 {
+        (self.f)(p)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+pub struct SphereSdf {
+    center: Point3,
+    radius: f64,
+}
+
+impl SphereSdf {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SphereSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        (p - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        AABB::from_points(self.center - rvec, self.center + rvec)
+    }
+}
+
+// Axis-aligned box, optionally with its edges rounded by `rounding`.
+pub struct BoxSdf {
+    center: Point3,
+    half_extents: Vec3,
+    rounding: f64,
+}
+
+impl BoxSdf {
+    pub fn new(center: Point3, half_extents: Vec3, rounding: f64) -> Self {
+        Self {
+            center,
+            half_extents,
+            rounding,
+        }
+    }
+}
+
+impl Sdf for BoxSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let q = Vec3::new(
+            p.x.abs() - self.half_extents.x,
+            p.y.abs() - self.half_extents.y,
+            p.z.abs() - self.half_extents.z,
+        );
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside - self.rounding
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let r = Vec3::new(self.rounding, self.rounding, self.rounding);
+        let extent = self.half_extents + r;
+        AABB::from_points(self.center - extent, self.center + extent)
+    }
+}
+
+// Torus around the y axis.
+pub struct TorusSdf {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl TorusSdf {
+    pub fn new(center: Point3, major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for TorusSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let ring = p.x.hypot(p.z) - self.major_radius;
+        ring.hypot(p.y) - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        AABB::from_points(self.center - extent, self.center + extent)
+    }
+}
+
+pub struct UnionSdf {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl UnionSdf {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for UnionSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::from_boxes(self.a.bounding_box(), self.b.bounding_box())
+    }
+}
+
+pub struct IntersectionSdf {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl IntersectionSdf {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for IntersectionSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let (box_a, box_b) = (self.a.bounding_box(), self.b.bounding_box());
+        AABB {
+            x: box_a.x.intersection(&box_b.x),
+            y: box_a.y.intersection(&box_b.y),
+            z: box_a.z.intersection(&box_b.z),
+        }
+    }
+}
+
+// `a` with `b` carved out of it.
+pub struct DifferenceSdf {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl DifferenceSdf {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for DifferenceSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.a.bounding_box()
+    }
+}
+
+// Polynomial smooth minimum; `k` is roughly the width of the blend.
+pub struct SmoothUnionSdf {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SmoothUnionSdf {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnionSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        let (da, db) = (self.a.distance(p), self.b.distance(p));
+        let h = (self.k - (da - db).abs()).max(0.0) / self.k;
+        da.min(db) - h * h * self.k / 4.0
+    }
+
+    // The blend can bulge out by at most k/4.
+    fn bounding_box(&self) -> AABB {
+        let bbox = AABB::from_boxes(self.a.bounding_box(), self.b.bounding_box());
+        let k = self.k / 4.0;
+        AABB::from_points(
+            Point3::new(bbox.x.min - k, bbox.y.min - k, bbox.z.min - k),
+            Point3::new(bbox.x.max + k, bbox.y.max + k, bbox.z.max + k),
+        )
+    }
+}
+
+// Twists space around the y axis by `rate` radians per unit of height.
+pub struct TwistSdf {
+    inner: Arc<dyn Sdf>,
+    rate: f64,
+    // Twisting stretches distances; dividing by this keeps the bound valid.
+    lipschitz: f64,
+    bbox: AABB,
+}
+
+impl TwistSdf {
+    pub fn new(inner: Arc<dyn Sdf>, rate: f64) -> Self {
+        let inner_box = inner.bounding_box();
+        let radius = [inner_box.x.min, inner_box.x.max]
+            .into_iter()
+            .flat_map(|x| [inner_box.z.min, inner_box.z.max].map(|z| x.hypot(z)))
+            .fold(0.0, f64::max);
+        let bbox = AABB::from_points(
+            Point3::new(-radius, inner_box.y.min, -radius),
+            Point3::new(radius, inner_box.y.max, radius),
+        );
+        // The march samples the whole box, out to its corners.
+        let corner = radius.hypot(radius);
+        Self {
+            inner,
+            rate,
+            lipschitz: (1.0 + (rate * corner).powi(2)).sqrt(),
+            bbox,
+        }
+    }
+}
+
+impl Sdf for TwistSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        let (sin, cos) = (self.rate * p.y).sin_cos();
+        let q = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+        self.inner.distance(q) / self.lipschitz
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+// Repeats `inner` on a grid with `period` spacing, `count` copies either side
+// of the original along each axis. Keep `inner` within one cell.
+pub struct RepeatSdf {
+    inner: Arc<dyn Sdf>,
+    period: Vec3,
+    count: [u32; 3],
+}
+
+impl RepeatSdf {
+    pub fn new(inner: Arc<dyn Sdf>, period: Vec3, count: [u32; 3]) -> Self {
+        Self {
+            inner,
+            period,
+            count,
+        }
+    }
+
+    fn fold(&self, p: f64, period: f64, count: u32) -> f64 {
+        if period <= 0.0 {
+            return p;
+        }
+        let n = count as f64;
+        p - period * (p / period).round().clamp(-n, n)
+    }
+}
+
+impl Sdf for RepeatSdf {
+    fn distance(&self, p: Point3) -> f64 {
+        self.inner.distance(Point3::new(
+            self.fold(p.x, self.period.x, self.count[0]),
+            self.fold(p.y, self.period.y, self.count[1]),
+            self.fold(p.z, self.period.z, self.count[2]),
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let bbox = self.inner.bounding_box();
+        let reach = Vec3::new(
+            self.period.x * self.count[0] as f64,
+            self.period.y * self.count[1] as f64,
+            self.period.z * self.count[2] as f64,
+        );
+        AABB::from_points(
+            Point3::new(bbox.x.min, bbox.y.min, bbox.z.min) - reach,
+            Point3::new(bbox.x.max, bbox.y.max, bbox.z.max) + reach,
+        )
+    }
+}
+
+// Mandelbulb fractal with the usual distance estimator, scaled so the bulb
+// fills a sphere of roughly `scale` around `center`.
+pub struct Mandelbulb {
+    center: Point3,
+    scale: f64,
+    pub power: f64,
+    pub iterations: usize,
+}
+
+impl Mandelbulb {
+    pub fn new(center: Point3, scale: f64) -> Self {
+        Self {
+            center,
+            scale,
+            power: 8.0,
+            iterations: 12,
+        }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: Point3) -> f64 {
+        let c = (p - self.center) / self.scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+            if r < 1e-12 {
+                z = c;
+                r = z.length();
+                continue;
+            }
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+
+            let zr = r.powf(self.power);
+            z = Vec3::new(
+                theta.sin() * phi.cos(),
+                phi.sin() * theta.sin(),
+                theta.cos(),
+            ) * zr
+                + c;
+            r = z.length();
+        }
+
+        if r < 1e-12 {
+            return -self.scale;
+        }
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let extent = 1.2 * self.scale;
+        let rvec = Vec3::new(extent, extent, extent);
+        AABB::from_points(self.center - rvec, self.center + rvec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::EmptyMaterial;
+
+    fn hit_t(sdf: Arc<dyn Sdf>, r: &Ray) -> Option<f64> {
+        let object = SdfObject::new(sdf, Arc::new(EmptyMaterial));
+        let mut rec = HitRecord::default();
+        object
+            .hit(r, Interval::new(0.0, f64::INFINITY), &mut rec)
+            .then_some(rec.t)
+    }
+
+    #[test]
+    fn box_face_head_on() {
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        for rounding in [0.0, 0.1] {
+            let sdf = BoxSdf::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 1.0),
+                rounding,
+            );
+            let t = hit_t(Arc::new(sdf), &r).expect("ray missed the box");
+            assert!((t - (4.0 - rounding)).abs() < 1e-3, "t = {}", t);
+        }
+    }
+
+    #[test]
+    fn sphere_head_on() {
+        let sdf = Arc::new(SphereSdf::new(Point3::new(0.0, 0.0, 0.0), 1.0));
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let t = hit_t(sdf.clone(), &r).expect("ray missed the sphere");
+        assert!((t - 4.0).abs() < 1e-3, "t = {}", t);
+
+        // Leaving from the surface does not hit the point left from.
+        let r = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit_t(sdf, &r).is_none());
+    }
+}
@@ -0,0 +1,267 @@
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::onb::ONB;
+use crate::rtweekend::random_double;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+// Angular radius of the sun's disk, in degrees.
+const SUN_ANGULAR_RADIUS: f64 = 0.2667;
+// Luminance of the sun above the atmosphere in kcd/m^2, the sky's unit:
+// 128 klux of illuminance spread over the disk.
+const SUN_LUMINANCE: f64 = 1.88e6;
+// Chance of aiming a sample at the sun while it is up.
+const SUN_SAMPLE_FRACTION: f64 = 0.5;
+
+// The five coefficients of Perez's all-weather sky luminance distribution.
+#[derive(Debug, Clone, Copy)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    // Relative value at zenith angle theta and angle gamma from the sun.
+    fn value(&self, cos_theta: f64, gamma: f64) -> f64 {
+        (1.0 + self.a * (self.b / cos_theta.max(1e-3)).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos() * gamma.cos())
+    }
+}
+
+// Preetham, Shirley and Smits' analytic daylight model: clear to hazy sky
+// by turbidity, with the sun as a disk of the right angular size whose
+// colour comes from the air mass it shines through. Below the horizon is a
+// diffuse ground lit by both. Radiance is in kcd/m^2; set intensity to
+// 1000 for cd/m^2 with a photographic exposure.
+pub struct PhysicalSky {
+    sun_direction: Vec3,
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    // For chromaticity x, y and luminance Y: the Perez coefficients and the
+    // zenith value divided by the Perez function there.
+    perez: [Perez; 3],
+    zenith: [f64; 3],
+    ground: Color,
+    pub intensity: f64,
+    pub visible: bool,
+}
+
+impl PhysicalSky {
+    // The sun `elevation` degrees above the horizon and `azimuth` degrees
+    // clockwise from north, which is -z, with east along +x. Turbidity runs
+    // from about 2 (clear) to 10 (hazy).
+    pub fn new(turbidity: f64, ground_albedo: Color, elevation: f64, azimuth: f64) -> Self {
+        let (el, az) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_direction = Vec3::new(el.cos() * az.sin(), el.sin(), -el.cos() * az.cos());
+
+        // The model only covers the sun at or above the horizon; lower suns
+        // give the sky of one just setting.
+        let theta_s = PI / 2.0 - el.max(0.0);
+        let t = turbidity;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic =
+            |c: [f64; 4]| c[0] * theta_s.powi(3) + c[1] * theta_s.powi(2) + c[2] * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let perez = [
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+        ];
+        let values = [zenith_x, zenith_y, zenith_luminance];
+        let zenith = [0, 1, 2].map(|k| values[k] / perez[k].value(1.0, theta_s));
+
+        // Sunlight is dimmed by Rayleigh scattering and by haze along the
+        // air mass (Kasten and Young); the channels stand for 680, 550 and
+        // 440 nm.
+        let sun_radiance = if elevation > -SUN_ANGULAR_RADIUS {
+            let zenith_degrees = 90.0 - elevation.max(0.0);
+            let air_mass = 1.0
+                / (zenith_degrees.to_radians().cos()
+                    + 0.50572 * (96.07995 - zenith_degrees).powf(-1.6364));
+            let beta = 0.04608 * t - 0.04586;
+            let [r, g, b] =
+                [(0.0422, 0.68), (0.100, 0.55), (0.249, 0.44f64)].map(|(rayleigh, wavelength)| {
+                    let haze = beta * wavelength.powf(-1.3);
+                    SUN_LUMINANCE * (-air_mass * (rayleigh + haze)).exp()
+                });
+            Color::new(r, g, b)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+
+        let mut sky = Self {
+            sun_direction,
+            sun_radiance,
+            cos_sun_radius: SUN_ANGULAR_RADIUS.to_radians().cos(),
+            perez,
+            zenith,
+            ground: Color::new(0.0, 0.0, 0.0),
+            intensity: 1.0,
+            visible: true,
+        };
+        sky.ground = ground_albedo * sky.horizontal_irradiance() / PI;
+        sky
+    }
+
+    // The sun where it stands at local solar time `hour` on the given day,
+    // seen from `latitude` degrees north (negative for south).
+    pub fn from_date(
+        turbidity: f64,
+        ground_albedo: Color,
+        month: u32,
+        day: u32,
+        hour: f64,
+        latitude: f64,
+    ) -> Self {
+        const DAYS_BEFORE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let day_of_year = DAYS_BEFORE[(month.clamp(1, 12) - 1) as usize] + day;
+
+        let declination =
+            (-23.44f64).to_radians() * (2.0 * PI * (day_of_year as f64 + 10.0) / 365.0).cos();
+        let hour_angle = (15.0 * (hour - 12.0)).to_radians();
+        let phi = latitude.to_radians();
+
+        let sin_elevation =
+            phi.sin() * declination.sin() + phi.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+        let cos_azimuth = (declination.sin() - sin_elevation * phi.sin())
+            / (elevation.cos() * phi.cos()).max(1e-9);
+        let mut azimuth = cos_azimuth.clamp(-1.0, 1.0).acos().to_degrees();
+        if hour_angle > 0.0 {
+            azimuth = 360.0 - azimuth;
+        }
+
+        Self::new(turbidity, ground_albedo, elevation.to_degrees(), azimuth)
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    fn sun_up(&self) -> bool {
+        self.sun_radiance.x() > 0.0
+    }
+
+    // Sky radiance above the horizon, without the sun.
+    fn sky_radiance(&self, d: Vec3) -> Color {
+        let cos_theta = d.y;
+        let gamma = Vec3::dot(d, self.sun_direction).clamp(-1.0, 1.0).acos();
+        let [x, y, luminance] =
+            [0, 1, 2].map(|k| self.zenith[k] * self.perez[k].value(cos_theta, gamma));
+
+        // xyY to XYZ, then to linear sRGB.
+        let big_x = x * luminance / y;
+        let big_z = (1.0 - x - y) * luminance / y;
+        Color::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+        )
+    }
+
+    // Light falling on level ground from the sky and the sun, by summing
+    // the upper hemisphere over a grid.
+    fn horizontal_irradiance(&self) -> Color {
+        const STEPS: usize = 64;
+        let d_theta = PI / 2.0 / STEPS as f64;
+        let d_phi = 2.0 * PI / (2 * STEPS) as f64;
+
+        let mut irradiance = Color::new(0.0, 0.0, 0.0);
+        for i in 0..STEPS {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..2 * STEPS {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let d = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                irradiance += self.sky_radiance(d) * (theta.cos() * theta.sin() * d_theta * d_phi);
+            }
+        }
+
+        let sun_solid_angle = 2.0 * PI * (1.0 - self.cos_sun_radius);
+        irradiance + self.sun_radiance * (sun_solid_angle * self.sun_direction.y.max(0.0))
+    }
+
+    fn sun_fraction(&self) -> f64 {
+        if self.sun_up() {
+            SUN_SAMPLE_FRACTION
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Environment for PhysicalSky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let d = Vec3::unit_vector(direction);
+        if d.y <= 0.0 {
+            return self.ground * self.intensity;
+        }
+        let mut radiance = self.sky_radiance(d);
+        if Vec3::dot(d, self.sun_direction) >= self.cos_sun_radius {
+            radiance += self.sun_radiance;
+        }
+        radiance * self.intensity
+    }
+
+    // Directions are drawn either uniformly in the sun's cone or over the
+    // whole sphere, which suits the smooth sky and ground.
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        let sun_fraction = self.sun_fraction();
+        let mut pdf = (1.0 - sun_fraction) / (4.0 * PI);
+        let d = Vec3::unit_vector(direction);
+        if Vec3::dot(d, self.sun_direction) >= self.cos_sun_radius {
+            pdf += sun_fraction / (2.0 * PI * (1.0 - self.cos_sun_radius));
+        }
+        pdf
+    }
+
+    fn generate(&self) -> Vec3 {
+        if random_double() >= self.sun_fraction() {
+            return Vec3::random_unit_vector();
+        }
+        let cos_theta = 1.0 - random_double() * (1.0 - self.cos_sun_radius);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * random_double();
+        ONB::new(self.sun_direction).transform(Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        ))
+    }
+
+    fn visible(&self) -> bool {
+        self.visible
+    }
+}
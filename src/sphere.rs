@@ -86,7 +86,10 @@ impl Hittable for Sphere {
         }
 
         rec.t = root;
-        rec.p = r.at(rec.t);
+        // Project back onto the surface to remove the error of r.at(t), which
+        // grows with the distance travelled rather than with the sphere.
+        let offset = r.at(rec.t) - current_center;
+        rec.p = current_center + offset * (self.radius / offset.length());
         let outward_normal = (rec.p - current_center) / self.radius;
         rec.set_face_normal(r, outward_normal);
         Self::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
@@ -102,7 +105,7 @@ impl Hittable for Sphere {
         let mut rec = HitRecord::default();
         if !self.hit(
             &Ray::new(origin, direction),
-            Interval::new(0.001, INFINITY),
+            Interval::new(0.0, INFINITY),
             &mut rec,
         ) {
             return 0.0;
@@ -0,0 +1,86 @@
+use crate::bvh::BVHOptions;
+use crate::hittable_list::HittableList;
+use crate::linear_bvh::LinearBVH;
+use crate::material::Material;
+use crate::triangle::Triangle;
+use crate::vec3::Point3;
+use std::fs::File;
+use std::sync::Arc;
+
+// Loads an ASCII or binary STL file. STL carries no materials, so every
+// facet uses `mat`.
+pub fn load_stl_model(path: &str, scale: f64, mat: Arc<dyn Material>) -> HittableList {
+    let mut object = HittableList::new();
+    for triangle in read_stl(path, scale, mat) {
+        object.add(Arc::new(triangle));
+    }
+
+    let bvh = LinearBVH::new(&object.objects, &BVHOptions::sah(4, 16));
+    eprintln!("Loaded {}: {}", path, bvh.stats());
+    HittableList::from(Arc::new(bvh))
+}
+
+fn read_stl(path: &str, scale: f64, mat: Arc<dyn Material>) -> Vec<Triangle> {
+    let mut file = File::open(path).expect("Failed to open STL file");
+    let mesh = stl_io::read_stl(&mut file).expect("Failed to load STL file");
+
+    let positions: Vec<Point3> = mesh
+        .vertices
+        .iter()
+        .map(|v| Point3::new(v[0] as f64, v[1] as f64, v[2] as f64) * scale)
+        .collect();
+
+    mesh.faces
+        .iter()
+        .map(|face| {
+            let [i0, i1, i2] = face.vertices;
+            Triangle::new_with_points(positions[i0], positions[i1], positions[i2], mat.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+
+    const ALBEDO: Color = Color {
+        x: 0.2,
+        y: 0.4,
+        z: 0.6,
+    };
+
+    fn read_fixture(name: &str) -> Vec<Triangle> {
+        let mat = Arc::new(Lambertian::new(Arc::new(SolidColor::new(ALBEDO))));
+        read_stl(&format!("objects/{}", name), 1.0, mat)
+    }
+
+    fn check_prism(triangles: &[Triangle]) {
+        assert_eq!(triangles.len(), 8);
+
+        let bbox = Triangle::bounds(triangles);
+        assert_eq!((bbox.x.min, bbox.x.max), (0.0, 1.0));
+        assert_eq!((bbox.y.min, bbox.y.max), (0.0, 1.0));
+        assert_eq!(bbox.z.min, 0.0);
+        assert!((bbox.z.max - 0.866).abs() < 1e-6);
+
+        for triangle in triangles {
+            assert!((triangle.albedo() - ALBEDO).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn ascii_prism() {
+        check_prism(&read_fixture("prism_ascii.stl"));
+    }
+
+    #[test]
+    fn binary_matches_ascii() {
+        let ascii = read_fixture("prism_ascii.stl");
+        let binary = read_fixture("prism_binary.stl");
+        check_prism(&binary);
+        assert!(ascii.iter().zip(&binary).all(|(a, b)| a.same_vertices(b)));
+    }
+}
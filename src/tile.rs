@@ -0,0 +1,143 @@
+// Part of the image to render, for touching up one area of a finished
+// frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropRegion {
+    // Pixels x0..x1 across and y0..y1 down from the top left corner.
+    Pixels {
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    },
+    // The same as fractions of the image width and height.
+    Normalized {
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    },
+}
+
+impl CropRegion {
+    // Pixel bounds, clipped to the image and at least one pixel in size.
+    pub fn bounds(&self, width: usize, height: usize) -> Tile {
+        let (x0, y0, x1, y1) = match *self {
+            CropRegion::Pixels { x0, y0, x1, y1 } => (x0, y0, x1, y1),
+            CropRegion::Normalized { x0, y0, x1, y1 } => (
+                (x0.clamp(0.0, 1.0) * width as f64).floor() as usize,
+                (y0.clamp(0.0, 1.0) * height as f64).floor() as usize,
+                (x1.clamp(0.0, 1.0) * width as f64).ceil() as usize,
+                (y1.clamp(0.0, 1.0) * height as f64).ceil() as usize,
+            ),
+        };
+        let x0 = x0.min(width - 1);
+        let y0 = y0.min(height - 1);
+        Tile {
+            x0,
+            y0,
+            x1: x1.clamp(x0 + 1, width),
+            y1: y1.clamp(y0 + 1, height),
+        }
+    }
+}
+
+// The order tiles are handed out in. Workers take the next tile as they
+// finish one, so this is also roughly the order the image fills in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileOrder {
+    // Row by row from the top.
+    Scanline,
+    // Outwards from the centre, where the subject usually is.
+    Spiral,
+    // Along a Hilbert curve, which keeps consecutive tiles next to each
+    // other and so shares more of the scene in the caches.
+    Hilbert,
+}
+
+// A rectangle of pixels, x0..x1 by y0..y1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Tile {
+    pub fn width(&self) -> usize {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> usize {
+        self.y1 - self.y0
+    }
+
+    // Splits the rectangle into tiles of at most `size` pixels square.
+    pub fn split(&self, size: usize, order: TileOrder) -> Vec<Tile> {
+        let size = size.max(1);
+        let columns = self.width().div_ceil(size);
+        let rows = self.height().div_ceil(size);
+
+        let mut cells: Vec<(usize, usize)> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .collect();
+        match order {
+            TileOrder::Scanline => {}
+            TileOrder::Spiral => {
+                let cx = (columns as f64 - 1.0) / 2.0;
+                let cy = (rows as f64 - 1.0) / 2.0;
+                let key = |&(column, row): &(usize, usize)| {
+                    let dx = column as f64 - cx;
+                    let dy = row as f64 - cy;
+                    // Ring first, then clockwise around it.
+                    (dx.abs().max(dy.abs()), dy.atan2(dx))
+                };
+                cells.sort_by(|a, b| {
+                    let (ring_a, angle_a) = key(a);
+                    let (ring_b, angle_b) = key(b);
+                    ring_a.total_cmp(&ring_b).then(angle_a.total_cmp(&angle_b))
+                });
+            }
+            TileOrder::Hilbert => {
+                let n = columns.max(rows).next_power_of_two();
+                cells.sort_by_key(|&(column, row)| hilbert_index(n, column, row));
+            }
+        }
+
+        cells
+            .into_iter()
+            .map(|(column, row)| {
+                let x0 = self.x0 + column * size;
+                let y0 = self.y0 + row * size;
+                Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + size).min(self.x1),
+                    y1: (y0 + size).min(self.y1),
+                }
+            })
+            .collect()
+    }
+}
+
+// Distance along the Hilbert curve filling an n by n grid, n a power of two.
+fn hilbert_index(n: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        // Turn the quadrant so the curve inside it starts and ends in
+        // the right corners.
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
@@ -0,0 +1,252 @@
+use crate::aabb::AABB;
+use crate::quaternion::Quaternion;
+use crate::vec3::{Point3, Vec3};
+use std::ops::Mul;
+
+// Affine 3x4 matrix; the implicit last row is (0, 0, 0, 1).
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix {
+    m: [[f64; 4]; 3],
+}
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Self {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+        }
+    }
+
+    pub fn from_rows(m: [[f64; 4]; 3]) -> Self {
+        Self { m }
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        Point3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // Multiplies by the transpose of the linear part, which is how normals
+    // are carried across when `self` is the inverse matrix.
+    pub fn transposed_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[1][0] * v.y + m[2][0] * v.z,
+            m[0][1] * v.x + m[1][1] * v.y + m[2][1] * v.z,
+            m[0][2] * v.x + m[1][2] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn inverse(&self) -> Self {
+        let m = &self.m;
+        let inv_det = 1.0 / self.determinant();
+
+        let mut r = [[0.0; 4]; 3];
+        r[0][0] = (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det;
+        r[0][1] = (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det;
+        r[0][2] = (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det;
+        r[1][0] = (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det;
+        r[1][1] = (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det;
+        r[1][2] = (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det;
+        r[2][0] = (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det;
+        r[2][1] = (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det;
+        r[2][2] = (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det;
+
+        for row in r.iter_mut() {
+            row[3] = -(row[0] * m[0][3] + row[1] * m[1][3] + row[2] * m[2][3]);
+        }
+
+        Self { m: r }
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+    fn mul(self, rhs: Matrix) -> Matrix {
+        let a = &self.m;
+        let b = &rhs.m;
+        let mut r = [[0.0; 4]; 3];
+        for i in 0..3 {
+            for j in 0..4 {
+                r[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+            }
+            r[i][3] += a[i][3];
+        }
+        Matrix { m: r }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    m: Matrix,
+    inv: Matrix,
+}
+
+impl Transform {
+    pub fn from_matrix(m: Matrix) -> Self {
+        Self {
+            m,
+            inv: m.inverse(),
+        }
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        Self::from_matrix(Matrix::from_rows([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+        ]))
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        Self::from_matrix(Matrix::from_rows([
+            [factors.x, 0.0, 0.0, 0.0],
+            [0.0, factors.y, 0.0, 0.0],
+            [0.0, 0.0, factors.z, 0.0],
+        ]))
+    }
+
+    pub fn uniform_scale(factor: f64) -> Self {
+        Self::scale(Vec3::new(factor, factor, factor))
+    }
+
+    pub fn rotate(axis: Vec3, angle_degrees: f64) -> Self {
+        let a = Vec3::unit_vector(axis);
+        let (sin_theta, cos_theta) = angle_degrees.to_radians().sin_cos();
+        let t = 1.0 - cos_theta;
+        Self::from_matrix(Matrix::from_rows([
+            [
+                t * a.x * a.x + cos_theta,
+                t * a.x * a.y - sin_theta * a.z,
+                t * a.x * a.z + sin_theta * a.y,
+                0.0,
+            ],
+            [
+                t * a.x * a.y + sin_theta * a.z,
+                t * a.y * a.y + cos_theta,
+                t * a.y * a.z - sin_theta * a.x,
+                0.0,
+            ],
+            [
+                t * a.x * a.z - sin_theta * a.y,
+                t * a.y * a.z + sin_theta * a.x,
+                t * a.z * a.z + cos_theta,
+                0.0,
+            ],
+        ]))
+    }
+
+    // Scale, then rotate, then translate. The inverse is built directly
+    // rather than by inverting the product, as motion blur needs one per ray.
+    pub fn from_trs(translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        let r = rotation.to_rows();
+        let s = [scale.x, scale.y, scale.z];
+        let t = [translation.x, translation.y, translation.z];
+
+        let mut m = [[0.0; 4]; 3];
+        let mut inv = [[0.0; 4]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] = r[i][j] * s[j];
+                inv[i][j] = r[j][i] / s[i];
+            }
+            m[i][3] = t[i];
+        }
+        for row in inv.iter_mut() {
+            row[3] = -(row[0] * t[0] + row[1] * t[1] + row[2] * t[2]);
+        }
+
+        Self {
+            m: Matrix::from_rows(m),
+            inv: Matrix::from_rows(inv),
+        }
+    }
+
+    pub fn rotate_x(angle_degrees: f64) -> Self {
+        Self::rotate(Vec3::new(1.0, 0.0, 0.0), angle_degrees)
+    }
+
+    pub fn rotate_y(angle_degrees: f64) -> Self {
+        Self::rotate(Vec3::new(0.0, 1.0, 0.0), angle_degrees)
+    }
+
+    pub fn rotate_z(angle_degrees: f64) -> Self {
+        Self::rotate(Vec3::new(0.0, 0.0, 1.0), angle_degrees)
+    }
+
+    // Applies `self` first and `next` afterwards.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            m: next.m * self.m,
+            inv: self.inv * next.inv,
+        }
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.m.point(p)
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.m.vector(v)
+    }
+
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inv.transposed_vector(n)
+    }
+
+    pub fn inverse_point(&self, p: Point3) -> Point3 {
+        self.inv.point(p)
+    }
+
+    pub fn inverse_vector(&self, v: Vec3) -> Vec3 {
+        self.inv.vector(v)
+    }
+
+    pub fn bounding_box(&self, bbox: &AABB) -> AABB {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+        for i in 0..=1 {
+            let x = if i == 1 { bbox.x.max } else { bbox.x.min };
+            for j in 0..=1 {
+                let y = if j == 1 { bbox.y.max } else { bbox.y.min };
+                for k in 0..=1 {
+                    let z = if k == 1 { bbox.z.max } else { bbox.z.min };
+                    let p = self.point(Point3::new(x, y, z));
+
+                    min.x = min.x.min(p.x);
+                    max.x = max.x.max(p.x);
+                    min.y = min.y.min(p.y);
+                    max.y = max.y.max(p.y);
+                    min.z = min.z.min(p.z);
+                    max.z = max.z.max(p.z);
+                }
+            }
+        }
+
+        AABB::from_points(min, max)
+    }
+}
@@ -1,101 +1,132 @@
-use crate::rtweekend::random_double;
 use crate::{
     aabb::AABB,
     hittable::{HitRecord, Hittable},
-    hittable_list::HittableList,
     interval::Interval,
     material::Material,
     ray::Ray,
-    rtweekend,
     vec3::{Point3, Vec3},
 };
 use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct Triangle {
-    q: Point3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
+    // Kept exactly as given, so that triangles sharing an edge test against
+    // bit-identical vertices and cannot leak rays through the seam.
+    vertices: [Point3; 3],
     mat: Arc<dyn Material>,
     bbox: AABB,
     normal: Vec3,
-    D: f64,
     area: f64,
 }
 
 impl Triangle {
     pub fn new_with_points(q: Point3, a: Point3, b: Point3, mat: Arc<dyn Material>) -> Self {
-        Triangle::new_with_vector(q, a - q, b - q, mat)
-    }
-
-    pub fn new_with_vector(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
-        let n = Vec3::cross(u, v);
-        let normal = Vec3::unit_vector(n);
-        let D = Vec3::dot(q, normal);
-        let w = n / Vec3::dot(n, n);
-        let area = n.length() / 2.0;
-        let mut quad = Self {
-            q,
-            u,
-            v,
-            w,
+        let mut tri = Self {
+            vertices: [q, a, b],
             mat,
             bbox: AABB::empty(),
-            normal,
-            D,
-            area,
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            area: 0.0,
         };
-        quad.set_bounding_box();
-        quad
+        tri.set_vertices(q, a, b);
+        tri
+    }
+
+    pub fn vertices(&self) -> [Point3; 3] {
+        self.vertices
+    }
+
+    // Moves the corners, for deforming meshes.
+    pub fn set_vertices(&mut self, q: Point3, a: Point3, b: Point3) {
+        let n = Vec3::cross(a - q, b - q);
+        self.vertices = [q, a, b];
+        self.normal = Vec3::unit_vector(n);
+        self.area = n.length() / 2.0;
+        self.set_bounding_box();
     }
 
     fn set_bounding_box(&mut self) {
-        let diagonal1 = AABB::from_points(self.q, self.q + self.u + self.v);
-        let diagonal2 = AABB::from_points(self.q + self.u, self.q + self.v);
-        self.bbox = AABB::from_boxes(diagonal1, diagonal2);
+        let [p0, p1, p2] = self.vertices;
+        self.bbox = AABB::from_boxes(AABB::from_points(p0, p1), AABB::from_points(p2, p2));
         self.bbox.pad_to_minimums();
     }
+}
 
-    fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
-        let unit_interval = Interval::new(0.0, 1.0);
-        if !unit_interval.contains(a)
-            || !unit_interval.contains(b)
-            || !unit_interval.contains(a + b)
-        {
-            return false;
-        }
-        rec.u = a;
-        rec.v = b;
-        true
-    }
+fn permute(v: Vec3, kx: usize, ky: usize, kz: usize) -> Vec3 {
+    Vec3::new(v[kx], v[ky], v[kz])
 }
 
-impl Hittable for Triangle {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let denom = Vec3::dot(r.direction(), self.normal);
+// Watertight ray/triangle test from Woop, Benthin and Wald (JCGT 2013).
+// The vertices are moved into a space where the ray starts at the origin
+// and points down +z, so the edge functions are evaluated on exactly the
+// same values for both triangles sharing an edge. Returns t and the
+// barycentric weights of the three vertices.
+pub(crate) fn intersect_triangle(
+    vertices: &[Point3; 3],
+    r: &Ray,
+    ray_t: Interval,
+) -> Option<(f64, [f64; 3])> {
+    let dir = r.direction();
+    let abs_dir = Vec3::new(dir.x.abs(), dir.y.abs(), dir.z.abs());
+    let kz = if abs_dir.x > abs_dir.y {
+        if abs_dir.x > abs_dir.z { 0 } else { 2 }
+    } else if abs_dir.y > abs_dir.z {
+        1
+    } else {
+        2
+    };
+    if abs_dir[kz] == 0.0 {
+        return None;
+    }
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
 
-        if denom.abs() < 1e-6 {
-            return false;
-        }
+    let d = permute(dir, kx, ky, kz);
+    let sx = -d.x / d.z;
+    let sy = -d.y / d.z;
+    let sz = 1.0 / d.z;
 
-        let t = (self.D - Vec3::dot(self.normal, r.origin())) / denom;
+    let [p0, p1, p2] = vertices
+        .map(|p| permute(p - r.origin(), kx, ky, kz))
+        .map(|p| Vec3::new(p.x + sx * p.z, p.y + sy * p.z, p.z * sz));
 
-        if !ray_t.contains(t) {
-            return false;
-        }
+    let e0 = p1.x * p2.y - p1.y * p2.x;
+    let e1 = p2.x * p0.y - p2.y * p0.x;
+    let e2 = p0.x * p1.y - p0.y * p1.x;
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    let det = e0 + e1 + e2;
+    if det == 0.0 {
+        return None;
+    }
 
-        let intersection = r.at(t);
+    // Range-check the scaled distance before paying for the division.
+    let t_scaled = e0 * p0.z + e1 * p1.z + e2 * p2.z;
+    if det < 0.0 && (t_scaled > ray_t.min * det || t_scaled < ray_t.max * det) {
+        return None;
+    }
+    if det > 0.0 && (t_scaled < ray_t.min * det || t_scaled > ray_t.max * det) {
+        return None;
+    }
 
-        let planar_hitpt_vector = intersection - self.q;
-        let alpha = Vec3::dot(self.w, Vec3::cross(planar_hitpt_vector, self.v));
-        let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar_hitpt_vector));
+    let inv_det = 1.0 / det;
+    Some((t_scaled * inv_det, [e0 * inv_det, e1 * inv_det, e2 * inv_det]))
+}
 
-        if !Triangle::is_interior(alpha, beta, rec) {
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((t, [b0, b1, b2])) = intersect_triangle(&self.vertices, r, ray_t) else {
             return false;
-        }
+        };
 
+        // Interpolating the vertices is far more accurate than r.at(t).
+        let [v0, v1, v2] = self.vertices;
         rec.t = t;
-        rec.p = intersection;
+        rec.p = v0 * b0 + v1 * b1 + v2 * b2;
+        rec.u = b1;
+        rec.v = b2;
         rec.mat = Some(Arc::clone(&self.mat));
         rec.set_face_normal(r, self.normal);
 
@@ -106,3 +137,32 @@ impl Hittable for Triangle {
         self.bbox
     }
 }
+
+#[cfg(test)]
+impl Triangle {
+    // The colour the material gives a ray hitting the middle of the front.
+    pub fn albedo(&self) -> crate::color::Color {
+        let [p0, p1, p2] = self.vertices;
+        let centre = (p0 + p1 + p2) / 3.0;
+        let r = Ray::new(centre + self.normal, -self.normal);
+        let mut rec = HitRecord::default();
+        assert!(self.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+        let mut srec = crate::material::scatter_record::default();
+        let mat = rec.mat.clone().unwrap();
+        mat.scatter(&r, &rec, &mut srec);
+        srec.attenuation
+    }
+
+    pub fn same_vertices(&self, other: &Triangle) -> bool {
+        (0..3).all(|i| (0..3).all(|k| self.vertices[i][k] == other.vertices[i][k]))
+    }
+
+    // The box around the corners of `triangles`, without any padding.
+    pub fn bounds(triangles: &[Triangle]) -> AABB {
+        triangles.iter().fold(AABB::empty(), |bbox, t| {
+            let [p0, p1, p2] = t.vertices;
+            let corners = AABB::from_boxes(AABB::from_points(p0, p1), AABB::from_points(p2, p2));
+            AABB::from_boxes(bbox, corners)
+        })
+    }
+}